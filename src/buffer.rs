@@ -1,14 +1,19 @@
 pub use vk::{BufferUsageFlags, MemoryPropertyFlags};
 
-use std::{mem::size_of_val, sync::{Arc, Mutex}};
-use ash::vk::{self, Handle};
-use gpu_allocator::{vulkan::{Allocation, AllocationCreateDesc, AllocationScheme, Allocator}, MemoryLocation};
-use crate::Vust;
+use std::{marker::PhantomData, mem::{size_of, size_of_val}, sync::mpsc};
+use ash::vk;
+use bytemuck::Pod;
+use gpu_allocator::{vulkan::{Allocation, AllocationCreateDesc, AllocationScheme}, MemoryLocation};
+use crate::{dynamic_buffer::DynamicBuffer, error::VustError, Vust};
 
 pub struct Buffer {
+    // only there to tell buffers apart in the debugger
     #[cfg(debug_assertions)]
+    #[allow(dead_code)]
     name: String,
     handle: vk::Buffer,
+    /// size asked for in bytes, the allocation can be bigger
+    size: u64,
     memory: Option<Allocation>,
     usage: vk::BufferUsageFlags,
     vust: Vust
@@ -20,42 +25,154 @@ impl Buffer {
             #[cfg(debug_assertions)]
             name: "Default".to_string(),
             data: &[],
+            capacity: 0,
             usage: vk::BufferUsageFlags::empty(),
             memory_location: vk::MemoryPropertyFlags::empty()
         }
     }
 
-    pub fn overwrite_all<T>(&self, data: &[T]) -> Result<(), &str> {
-        if let Some(memory) = &self.memory {
-            if memory.size() < size_of_val(data) as u64 {
-                return Err("data size is bigger than buffer size");
-            }
+    /// Device local buffers are written through a staging buffer, which waits for the copy to finish
+    pub fn overwrite_all<T: Pod>(&self, data: &[T]) -> Result<(), VustError> {
+        // memory is only taken out in drop
+        let memory = self.memory.as_ref().unwrap();
+        if self.size < size_of_val(data) as u64 {
+            return Err(VustError::BufferOverflow { data_size: size_of_val(data) as u64, buffer_size: self.size });
+        }
 
-            unsafe {
-                memory.mapped_ptr().unwrap().as_ptr().cast::<T>().copy_from_nonoverlapping(data.as_ptr(), data.len());
+        match memory.mapped_ptr() {
+            Some(mapped_ptr) => unsafe {
+                mapped_ptr.as_ptr().cast::<T>().copy_from_nonoverlapping(data.as_ptr(), data.len());
                 Ok(())
             }
-        } else {
-            Err("buffer not created somehow??")
+            None => self.write_staged(data, 0)
         }
     }
 
 	/// offset is amount of 'T's
-	pub fn overwrite_offset<T>(&self, data: &[T], offset: usize) -> Result<(), &str> {
-		if let Some(memory) = &self.memory {
-			if memory.size() < size_of_val(data) as u64 {
-				return Err("data size is bigger than buffer size");
-			}
+	/// 
+	/// Device local buffers are written through a staging buffer, which waits for the copy to finish
+	pub fn overwrite_offset<T: Pod>(&self, data: &[T], offset: usize) -> Result<(), VustError> {
+		// memory is only taken out in drop
+		let memory = self.memory.as_ref().unwrap();
+		// a huge offset would wrap around and pass the check
+		let end = offset.checked_mul(size_of::<T>())
+			.and_then(|start| start.checked_add(size_of_val(data)))
+			.map_or(u64::MAX, |end| end as u64);
+		if self.size < end {
+			return Err(VustError::BufferOverflow { data_size: end, buffer_size: self.size });
+		}
 
-			unsafe {
-				memory.mapped_ptr().unwrap().as_ptr().cast::<T>().add(offset).copy_from_nonoverlapping(data.as_ptr(), data.len());
+		match memory.mapped_ptr() {
+			Some(mapped_ptr) => unsafe {
+				mapped_ptr.as_ptr().cast::<T>().add(offset).copy_from_nonoverlapping(data.as_ptr(), data.len());
 				Ok(())
 			}
-		} else {
-			Err("buffer not created somehow??")
+			None => self.write_staged(data, (offset * size_of::<T>()) as u64)
 		}
 	}
 
+    /// Copies data into a host visible staging buffer, then into this buffer with cmd_copy_buffer
+    fn write_staged<T: Pod>(&self, data: &[T], dst_offset: u64) -> Result<(), VustError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let staging_buffer = Buffer::builder()
+            .with_name("Staging Buffer")
+            .with_data(data)
+            .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+            .build(&self.vust, true)?;
+
+        let command_buffer = self.vust.begin_single_exec_command()?;
+        unsafe {
+            // frames submitted before this might still be reading the old data
+            self.vust.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[]
+            );
+            self.vust.device.cmd_copy_buffer(
+                command_buffer,
+                staging_buffer.handle,
+                self.handle,
+                &[vk::BufferCopy::builder()
+                    .src_offset(0)
+                    .dst_offset(dst_offset)
+                    .size(size_of_val(data) as u64)
+                    .build()]
+            );
+            self.vust.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::MEMORY_READ)
+                    .build()],
+                &[],
+                &[]
+            );
+        }
+        // the render thread submits it ahead of the frame its recording, this waits for the copy so the staging buffer can be dropped after
+        self.vust.end_single_exec_command(command_buffer)
+    }
+
+    /// Reads the whole buffer back as 'T's
+    /// 
+    /// Host visible buffers are read straight away, make sure the gpu is done writing to them (Vust.wait_idle(), or use read_async()).
+    /// Device local buffers are copied back by the render thread like read_async() but in their own submission, this blocks until that copy is done
+    pub fn read<T: Pod>(&self) -> Result<Vec<T>, VustError> {
+        // memory is only taken out in drop
+        let memory = self.memory.as_ref().unwrap();
+
+        match memory.mapped_ptr() {
+            Some(mapped_ptr) => unsafe {
+                let bytes = std::slice::from_raw_parts(mapped_ptr.as_ptr().cast::<u8>(), self.size as usize);
+                Ok(bytemuck::pod_collect_to_vec(bytes))
+            }
+            None => {
+                self.vust.check_not_render_thread()?;
+
+                let (sender, receiver) = mpsc::channel();
+                self.vust.read_buffer_now(self.handle, self.size, Box::new(move |bytes| {
+                    let _ = sender.send(bytes.to_vec());
+                }))?;
+
+                // the sender is dropped without sending if the render thread dies
+                let bytes = receiver.recv().map_err(|_| VustError::RenderThreadDead)?;
+                Ok(bytemuck::pod_collect_to_vec(&bytes))
+            }
+        }
+    }
+
+    /// Reads the buffer back at the end of the next frame that gets rendered, the vec arrives once the gpu has finished that frame
+    /// 
+    /// The receiver disconnects if the render thread dies. Returns VustError::BufferNotReadable for HOST_VISIBLE buffers built without TRANSFER_SRC
+    pub fn read_async<T: Pod + Send>(&self) -> Result<mpsc::Receiver<Vec<T>>, VustError> {
+        if !self.usage.contains(vk::BufferUsageFlags::TRANSFER_SRC) {
+            return Err(VustError::BufferNotReadable);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.vust.read_buffer(self.handle, self.size, Box::new(move |bytes| {
+            // receiver being dropped just means nobody wants the data anymore
+            let _ = sender.send(bytemuck::pod_collect_to_vec(bytes));
+        }))?;
+
+        Ok(receiver)
+    }
+
+    /// Size in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
     pub fn handle(&self) -> vk::Buffer {
         self.handle
     }
@@ -63,7 +180,8 @@ impl Buffer {
 
 impl Drop for Buffer {
     fn drop(&mut self) {
-        self.vust.destroy_buffer(self.handle, self.memory.take().unwrap());
+        // if the render thread is already dead theres nothing left to free it
+        let _ = self.vust.destroy_buffer(self.handle, self.memory.take().unwrap());
     }
 }
 
@@ -71,6 +189,7 @@ pub struct BufferBuilder<'a, T> {
     #[cfg(debug_assertions)]
     name: String,
     data: &'a [T],
+    capacity: usize,
     usage: vk::BufferUsageFlags,
     memory_location: vk::MemoryPropertyFlags
 }
@@ -83,7 +202,7 @@ impl<'a, T> BufferBuilder<'a, T> {
     }
 
     #[cfg(not(debug_assertions))]
-    pub fn with_name(mut self, _name: &str) -> Self {
+    pub fn with_name(self, _name: &str) -> Self {
         self
     }
 
@@ -92,6 +211,12 @@ impl<'a, T> BufferBuilder<'a, T> {
         self
     }
 
+    /// Amount of 'T's the buffer has room for, the buffer is never smaller than the data
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
     pub fn with_usage(mut self, usage: vk::BufferUsageFlags) -> Self {
         self.usage = usage;
         self
@@ -102,28 +227,36 @@ impl<'a, T> BufferBuilder<'a, T> {
         self
     }
 
-    /// currently only host visible and device local memory are supported
+    /// HOST_VISIBLE | HOST_CACHED is for reading back on the cpu (gpu to cpu), HOST_VISIBLE is for writing from the cpu (cpu to gpu),
+    /// DEVICE_LOCAL without HOST_VISIBLE stays on the gpu
+    /// 
+    /// Device local buffers get TRANSFER_DST added to their usage, they're filled through a staging buffer.
+    /// Device local and HOST_CACHED buffers get TRANSFER_SRC so they can be read back, add it yourself to read_async() other host visible buffers
     /// 
     /// write_on_creation - if true, the buffer will be written to on creation
-    pub fn build(self, vust: &Vust, write_on_creation: bool) -> Buffer {
+    pub fn build(self, vust: &Vust, write_on_creation: bool) -> Result<Buffer, VustError> where T: Pod {
+        let (location, usage) = if self.memory_location.contains(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED) {
+            (MemoryLocation::GpuToCpu, self.usage | vk::BufferUsageFlags::TRANSFER_SRC)
+        } else if self.memory_location.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            // read straight from the mapped memory, only read_async() would need TRANSFER_SRC
+            (MemoryLocation::CpuToGpu, self.usage)
+        } else if self.memory_location.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL) {
+            (MemoryLocation::GpuOnly, self.usage | vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::TRANSFER_SRC)
+        } else {
+            return Err(VustError::NoSuitableMemoryType);
+        };
+        let size = (self.capacity.max(self.data.len()) * size_of::<T>()) as u64;
+
         unsafe {
             let buffer_create_info = vk::BufferCreateInfo::builder()
-                .size(size_of_val(self.data) as u64)
-                .usage(self.usage)
+                .size(size)
+                .usage(usage)
                 .sharing_mode(vk::SharingMode::EXCLUSIVE)
                 .build();
 
-            let buffer = vust.device.create_buffer(&buffer_create_info, None).unwrap();
+            let buffer = vust.device.create_buffer(&buffer_create_info, None)?;
 
             let memory_requirements = vust.device.get_buffer_memory_requirements(buffer);
-        
-            let location = if self.memory_location.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
-                MemoryLocation::CpuToGpu
-            } else if self.memory_location.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL) {
-                MemoryLocation::GpuOnly
-            } else {
-                unimplemented!("not supported")
-            };
 
             let memory_allocate_info = AllocationCreateDesc {
                 #[cfg(debug_assertions)]
@@ -136,22 +269,117 @@ impl<'a, T> BufferBuilder<'a, T> {
                 allocation_scheme: AllocationScheme::GpuAllocatorManaged
             };
         
-            let memory = vust.memory_allocator.lock().unwrap().allocate(&memory_allocate_info).unwrap();
+            let memory = vust.memory_allocator.lock().unwrap().allocate(&memory_allocate_info)?;
 
-            vust.device.bind_buffer_memory(buffer, memory.memory(), memory.offset()).unwrap();
+            vust.device.bind_buffer_memory(buffer, memory.memory(), memory.offset())?;
 
-            if write_on_creation {
-                memory.mapped_ptr().unwrap().as_ptr().cast::<T>().copy_from_nonoverlapping(self.data.as_ptr(), self.data.len());
-            }
-
-            Buffer {
+            let buffer = Buffer {
                 #[cfg(debug_assertions)]
                 name: self.name,
                 handle: buffer,
+                size,
                 memory: Some(memory),
-                usage: self.usage,
+                usage,
                 vust: vust.clone()
+            };
+
+            if write_on_creation {
+                buffer.overwrite_all(self.data)?;
             }
+
+            Ok(buffer)
+        }
+    }
+}
+
+impl<'a, T: Pod> BufferBuilder<'a, T> {
+    /// Same as build but keeps the element type, the data is always written
+    pub fn build_typed(self, vust: &Vust) -> Result<TypedBuffer<T>, VustError> {
+        let len = self.data.len();
+        let buffer = self.build(vust, true)?;
+
+        Ok(TypedBuffer {
+            capacity: buffer.size as usize / size_of::<T>(),
+            buffer,
+            len,
+            _marker: PhantomData
+        })
+    }
+
+    /// Buffer that grows when pushed past its capacity, see DynamicBuffer
+    pub fn build_dynamic(self, vust: &Vust) -> Result<DynamicBuffer<T>, VustError> {
+        #[cfg(debug_assertions)]
+        let name = self.name;
+        #[cfg(not(debug_assertions))]
+        let name = String::new();
+
+        DynamicBuffer::new(vust, name, self.data.to_vec(), self.capacity, self.usage, self.memory_location)
+    }
+}
+
+/// Buffer that knows its element type and how many elements are in it
+/// 
+/// Built with BufferBuilder::build_typed(), T has to be Pod so it can be copied to the gpu byte for byte
+pub struct TypedBuffer<T: Pod> {
+    buffer: Buffer,
+    len: usize,
+    capacity: usize,
+    _marker: PhantomData<T>
+}
+
+impl<T: Pod> TypedBuffer<T> {
+    /// Replaces the contents, len becomes data.len()
+    pub fn write(&mut self, data: &[T]) -> Result<(), VustError> {
+        self.check_bounds(data.len())?;
+
+        self.buffer.overwrite_all(data)?;
+        self.len = data.len();
+        Ok(())
+    }
+
+    /// offset is amount of 'T's, len grows if the write goes past it
+    pub fn write_offset(&mut self, data: &[T], offset: usize) -> Result<(), VustError> {
+        let end = offset.checked_add(data.len()).ok_or(VustError::BufferOverflow { data_size: u64::MAX, buffer_size: (self.capacity * size_of::<T>()) as u64 })?;
+        self.check_bounds(end)?;
+
+        self.buffer.overwrite_offset(data, offset)?;
+        self.len = self.len.max(end);
+        Ok(())
+    }
+
+    fn check_bounds(&self, end: usize) -> Result<(), VustError> {
+        if end > self.capacity {
+            return Err(VustError::BufferOverflow { data_size: end.checked_mul(size_of::<T>()).map_or(u64::MAX, |size| size as u64), buffer_size: (self.capacity * size_of::<T>()) as u64 });
         }
+
+        Ok(())
+    }
+
+    /// Amount of 'T's written to the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Amount of 'T's the buffer has room for
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Size of the written elements in bytes
+    pub fn size_in_bytes(&self) -> u64 {
+        (self.len * size_of::<T>()) as u64
+    }
+
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer.handle
+    }
+
+    /// Untyped buffer underneath
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
     }
 }
@@ -0,0 +1,78 @@
+use std::sync::mpsc;
+use ash::vk;
+use gpu_allocator::vulkan::Allocation;
+
+/// A copy of a rendered frame, sent back by Vust.capture_frame()
+#[derive(Debug, Clone)]
+pub struct CapturedImage {
+    /// tightly packed RGBA8, row by row starting from the top left
+    pub pixels: Vec<u8>,
+    pub extent: vk::Extent2D,
+    /// format of the swapchain/headless image the pixels were converted from
+    pub format: vk::Format
+}
+
+/// Readback buffer waiting for its frame's fence before the pixels can be read
+pub(crate) struct PendingCapture {
+    pub(crate) frame: usize,
+    pub(crate) buffer: vk::Buffer,
+    pub(crate) allocation: Allocation,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) format: vk::Format,
+    pub(crate) senders: Vec<mpsc::Sender<CapturedImage>>
+}
+
+/// Size of one pixel in bytes, None if the format cant be converted to RGBA8
+pub(crate) fn bytes_per_pixel(format: vk::Format) -> Option<u64> {
+    match format {
+        vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM |
+        vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM |
+        vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => Some(4),
+        vk::Format::R16G16B16A16_SFLOAT => Some(8),
+        _ => None
+    }
+}
+
+/// Converts raw image data to RGBA8, values outside 0..1 (scRGB) are clamped
+pub(crate) fn to_rgba8(format: vk::Format, data: &[u8]) -> Vec<u8> {
+    match format {
+        vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => {
+            data.chunks_exact(4).flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]]).collect()
+        }
+        vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => {
+            data.chunks_exact(4).flat_map(|pixel| {
+                let packed = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                // drop the bottom 2 bits of each 10 bit channel
+                let low = ((packed >> 2) & 0xFF) as u8;
+                let middle = ((packed >> 12) & 0xFF) as u8;
+                let high = ((packed >> 22) & 0xFF) as u8;
+                let alpha = ((packed >> 30) * 85) as u8;
+
+                if format == vk::Format::A2B10G10R10_UNORM_PACK32 {
+                    [low, middle, high, alpha]
+                } else {
+                    [high, middle, low, alpha]
+                }
+            }).collect()
+        }
+        vk::Format::R16G16B16A16_SFLOAT => {
+            data.chunks_exact(2)
+                .map(|half| (f16_to_f32(u16::from_ne_bytes([half[0], half[1]])).clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect()
+        }
+        _ => data.to_vec()
+    }
+}
+
+// no half crate for one function
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1F => if mantissa == 0.0 { sign * f32::INFINITY } else { f32::NAN },
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15)
+    }
+}
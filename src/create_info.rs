@@ -1,6 +1,9 @@
-use std::ffi::CString;
-use ash::vk::{self, VertexInputAttributeDescription, VertexInputBindingDescription};
-use crate::pipeline::GraphicsPipeline;
+pub use ash::vk::{PresentModeKHR, SurfaceFormatKHR, ColorSpaceKHR};
+
+use std::ffi::{CStr, CString};
+use ash::{extensions, vk};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
+use crate::{error::VustError, Vust};
 
 pub struct VustCreateInfo {
     pub(super) app_name: CString,
@@ -9,7 +12,12 @@ pub struct VustCreateInfo {
     pub(super) enabled_instance_extensions: Vec<CString>,
     pub(super) choose_physical_device: fn(PhysicalDevice) -> bool,
     pub(super) surface_create_info: SurfaceCreateInfo,
-    pub(super) framebuffer_size: (usize, usize)
+    pub(super) framebuffer_size: (usize, usize),
+    pub(super) present_modes: Vec<vk::PresentModeKHR>,
+    pub(super) surface_formats: Vec<vk::SurfaceFormatKHR>,
+    pub(super) load_op: LoadOp,
+    pub(super) frames_in_flight: usize,
+    pub(super) msaa_samples: u32
 }
 
 impl Default for VustCreateInfo {
@@ -23,13 +31,29 @@ impl Default for VustCreateInfo {
             choose_physical_device: |physical_device| {
                 match physical_device.device_type {
                     PhysicalDeviceType::Discrete | PhysicalDeviceType::Integrated => true,
-                    PhysicalDeviceType::NotSupported => false
+                    PhysicalDeviceType::Cpu | PhysicalDeviceType::NotSupported => false
                 }
             },
 
             surface_create_info: SurfaceCreateInfo::None,
 
-            framebuffer_size: (0, 0)
+            framebuffer_size: (0, 0),
+
+            // vsync on by default
+            present_modes: vec![vk::PresentModeKHR::FIFO],
+
+            surface_formats: vec![
+                vk::SurfaceFormatKHR { format: vk::Format::B8G8R8A8_SRGB, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
+                vk::SurfaceFormatKHR { format: vk::Format::R8G8B8A8_SRGB, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
+                vk::SurfaceFormatKHR { format: vk::Format::B8G8R8A8_UNORM, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
+                vk::SurfaceFormatKHR { format: vk::Format::R8G8B8A8_UNORM, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR }
+            ],
+
+            load_op: LoadOp::Clear,
+
+            frames_in_flight: Vust::DEFAULT_FRAMES_IN_FLIGHT,
+
+            msaa_samples: 1
         }
     }
 }
@@ -46,14 +70,16 @@ impl VustCreateInfo {
         self
     }
 
+    /// Extensions needed by the surface (VK_KHR_surface, VK_KHR_xlib_surface etc.) are added automatically
     pub fn with_extensions(mut self, extensions: Vec<impl Into<Vec<u8>>>) -> Self {
         self.enabled_instance_extensions = extensions.into_iter().map(|ext| CString::new(ext).unwrap()).collect();
         self
     }
 
     /// Optional - if not provided, will choose first discrete or integrated device
-    pub fn with_choose_physical_device(mut self, choose_physical_device: fn(PhysicalDevice) -> bool) {
+    pub fn with_choose_physical_device(mut self, choose_physical_device: fn(PhysicalDevice) -> bool) -> Self {
         self.choose_physical_device = choose_physical_device;
+        self
     }
 
     pub fn with_surface_create_info(mut self, surface_create_info: SurfaceCreateInfo) -> Self {
@@ -61,10 +87,93 @@ impl VustCreateInfo {
         self
     }
 
+    /// Derives the surface create info from any window that implements raw-window-handle (winit, glfw, sdl..)
+    /// 
+    /// The instance extensions it needs are picked automatically, framebuffer size still has to be given
+    pub fn with_window(mut self, window: &(impl HasWindowHandle + HasDisplayHandle)) -> Result<Self, VustError> {
+        self.surface_create_info = SurfaceCreateInfo::from_window(window)?;
+        Ok(self)
+    }
+
     pub fn with_framebuffer_size(mut self, framebuffer_size: (usize, usize)) -> Self {
         self.framebuffer_size = framebuffer_size;
         self
     }
+
+    /// Present modes in order of preference, the first one the surface supports is used
+    /// 
+    /// Falls back to FIFO (always supported) if none of them are
+    pub fn with_present_modes(mut self, present_modes: Vec<vk::PresentModeKHR>) -> Self {
+        self.present_modes = present_modes;
+        self
+    }
+
+    /// Surface formats in order of preference, the first one the surface supports is used
+    /// 
+    /// If none of them are supported, the first SRGB_NONLINEAR format the surface has is used, then whatever it reports first.
+    /// The chosen format can be read back with Vust::surface_format()
+    pub fn with_surface_formats(mut self, surface_formats: Vec<vk::SurfaceFormatKHR>) -> Self {
+        self.surface_formats = surface_formats;
+        self
+    }
+
+    /// Puts the hdr format in front of the surface format preference list, vust still falls back to sdr if the surface doesnt support it
+    /// 
+    /// VK_EXT_swapchain_colorspace is enabled automatically when the driver has it
+    pub fn with_hdr(mut self, hdr: Hdr) -> Self {
+        self.surface_formats.insert(0, hdr.surface_format());
+        self
+    }
+
+    /// What happens to the color image at the start of every frame, can be changed later with Vust::set_load_op()
+    pub fn with_load_op(mut self, load_op: LoadOp) -> Self {
+        self.load_op = load_op;
+        self
+    }
+
+    /// How many frames the cpu can record ahead of the gpu, at least 1
+    /// 
+    /// 1 has the lowest latency, 3 keeps the gpu busier in heavy scenes
+    pub fn with_frames_in_flight(mut self, frames_in_flight: usize) -> Self {
+        self.frames_in_flight = frames_in_flight.max(1);
+        self
+    }
+
+    /// Samples per pixel for the main pass (1, 2, 4, 8..), resolved onto the swapchain image at the end of the pass
+    /// 
+    /// Clamped to the highest count the gpu supports for color and depth, Vust::samples() has the one that was used.
+    /// Pipelines from GraphicsPipeline::new() pick it up automatically, render targets stay single sampled
+    pub fn with_msaa(mut self, samples: u32) -> Self {
+        self.msaa_samples = samples.max(1);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadOp {
+    /// clear to the color set with Vust::set_clear_color()
+    Clear,
+    /// keep what was rendered to the image before, depth/stencil is still cleared
+    /// 
+    /// the swapchain image can be from a few frames ago, not necessarily the last frame.
+    /// Images that were never rendered to (first frames, after a resize) are cleared
+    Load
+}
+
+pub enum Hdr {
+    /// A2B10G10R10_UNORM_PACK32 with the ST2084 (PQ) transfer function
+    Hdr10,
+    /// R16G16B16A16_SFLOAT in linear extended srgb
+    ScRgb
+}
+
+impl Hdr {
+    pub fn surface_format(&self) -> vk::SurfaceFormatKHR {
+        match self {
+            Hdr::Hdr10 => vk::SurfaceFormatKHR { format: vk::Format::A2B10G10R10_UNORM_PACK32, color_space: vk::ColorSpaceKHR::HDR10_ST2084_EXT },
+            Hdr::ScRgb => vk::SurfaceFormatKHR { format: vk::Format::R16G16B16A16_SFLOAT, color_space: vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT }
+        }
+    }
 }
 
 pub struct PhysicalDevice {
@@ -75,6 +184,8 @@ pub struct PhysicalDevice {
 pub enum PhysicalDeviceType {
     Discrete,
     Integrated,
+    /// software rasterizers like lavapipe or swiftshader, not chosen by default
+    Cpu,
     NotSupported
 }
 
@@ -83,14 +194,79 @@ pub enum SurfaceCreateInfo {
         hinstance: *const std::ffi::c_void,
         hwnd: *const std::ffi::c_void
     },
+    /// Linux X11 through Xlib
+    Xlib {
+        display: *const std::ffi::c_void,
+        window: std::ffi::c_ulong
+    },
+    /// Linux X11 through XCB
+    Xcb {
+        connection: *const std::ffi::c_void,
+        window: u32
+    },
+    /// Linux Wayland
+    Wayland {
+        display: *const std::ffi::c_void,
+        surface: *const std::ffi::c_void
+    },
+    /// No window, vust renders into offscreen color/depth images instead of a swapchain
+    /// 
+    /// framebuffer_size is ignored, width and height are used instead. Useful for running on CI with a software driver (lavapipe),
+    /// pair it with with_choose_physical_device() that accepts PhysicalDeviceType::Cpu
+    Headless {
+        width: u32,
+        height: u32,
+        format: vk::Format
+    },
     None
 }
 
 impl SurfaceCreateInfo {
-    pub fn into_win32(self) -> (*const std::ffi::c_void, *const std::ffi::c_void) {
+    pub fn into_win32(self) -> Result<(*const std::ffi::c_void, *const std::ffi::c_void), VustError> {
         match self {
-            SurfaceCreateInfo::Win32 { hinstance, hwnd } => (hinstance, hwnd),
-            _ => panic!("surface create info is either None, or not supported yet")
+            SurfaceCreateInfo::Win32 { hinstance, hwnd } => Ok((hinstance, hwnd)),
+            _ => Err(VustError::UnsupportedSurface)
         }
     }
+
+    /// Returns VustError::UnsupportedSurface for platforms vust cant draw to or if the window is missing a handle vulkan needs (hinstance, xlib display, xcb connection)
+    pub fn from_window(window: &(impl HasWindowHandle + HasDisplayHandle)) -> Result<Self, VustError> {
+        let window_handle = window.window_handle().map_err(|_| VustError::UnsupportedSurface)?.as_raw();
+        let display_handle = window.display_handle().map_err(|_| VustError::UnsupportedSurface)?.as_raw();
+
+        Ok(match (window_handle, display_handle) {
+            (RawWindowHandle::Win32(window), _) => SurfaceCreateInfo::Win32 {
+                hinstance: window.hinstance.ok_or(VustError::UnsupportedSurface)?.get() as *const std::ffi::c_void,
+                hwnd: window.hwnd.get() as *const std::ffi::c_void
+            },
+            (RawWindowHandle::Xlib(window), RawDisplayHandle::Xlib(display)) => SurfaceCreateInfo::Xlib {
+                display: display.display.ok_or(VustError::UnsupportedSurface)?.as_ptr(),
+                window: window.window
+            },
+            (RawWindowHandle::Xcb(window), RawDisplayHandle::Xcb(display)) => SurfaceCreateInfo::Xcb {
+                connection: display.connection.ok_or(VustError::UnsupportedSurface)?.as_ptr(),
+                window: window.window.get()
+            },
+            (RawWindowHandle::Wayland(window), RawDisplayHandle::Wayland(display)) => SurfaceCreateInfo::Wayland {
+                display: display.display.as_ptr(),
+                surface: window.surface.as_ptr()
+            },
+            _ => return Err(VustError::UnsupportedSurface)
+        })
+    }
+
+    /// Instance extensions needed to create this surface, vust enables them automatically
+    pub fn required_instance_extensions(&self) -> Vec<&'static CStr> {
+        match self {
+            SurfaceCreateInfo::Win32 { .. } => vec![extensions::khr::Surface::name(), extensions::khr::Win32Surface::name()],
+            SurfaceCreateInfo::Xlib { .. } => vec![extensions::khr::Surface::name(), extensions::khr::XlibSurface::name()],
+            SurfaceCreateInfo::Xcb { .. } => vec![extensions::khr::Surface::name(), extensions::khr::XcbSurface::name()],
+            SurfaceCreateInfo::Wayland { .. } => vec![extensions::khr::Surface::name(), extensions::khr::WaylandSurface::name()],
+            SurfaceCreateInfo::Headless { .. } | SurfaceCreateInfo::None => Vec::new()
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self, SurfaceCreateInfo::Headless { .. })
+    }
 }
@@ -1,9 +1,15 @@
 use ash::vk;
-use crate::Vust;
 
 #[derive(Debug, Clone)]
 pub struct Descriptor {
+    // the sets are freed with it
+    #[allow(dead_code)]
     pub(super) descriptor_pool: vk::DescriptorPool,
-    pub(super) descriptor_set: [vk::DescriptorSet; Vust::MAX_FRAMES_IN_FLIGHT],
-    pub(super) write_descriptor_set_info: Vec<[vk::WriteDescriptorSet; Vust::MAX_FRAMES_IN_FLIGHT]>
+    /// one per frame in flight
+    pub(super) descriptor_set: Vec<vk::DescriptorSet>,
+    /// per binding, one per frame in flight
+    pub(super) write_descriptor_set_info: Vec<Vec<vk::WriteDescriptorSet>>
 }
+
+// the write infos are only templates, their pointers are null until they get filled in right before vkUpdateDescriptorSets
+unsafe impl Send for Descriptor {}
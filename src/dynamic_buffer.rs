@@ -0,0 +1,155 @@
+use ash::vk;
+use bytemuck::Pod;
+use crate::{buffer::{Buffer, TypedBuffer}, descriptor::Descriptor, error::VustError, write_descriptor_info::WriteDescriptorInfo, Vust};
+
+/// Buffer that reallocates itself when it runs out of room, for data that changes size every frame (ui, debug geometry..)
+///
+/// Capacity at least doubles on every grow. The old buffer is retired through Vust.destroy_buffer() so frames still using it keep working.
+/// A copy of the data is kept on the cpu to fill the new buffer with.
+///
+/// Descriptors given to track_descriptor() are pointed at the new buffer after every grow: the set of the frame being recorded right away, every other frame's set when that frame starts.
+/// Grow before binding the descriptor in a frame, a set cant be rewritten once its bound. Anything else has to use handle() again
+pub struct DynamicBuffer<T: Pod> {
+    name: String,
+    buffer: TypedBuffer<T>,
+    data: Vec<T>,
+    usage: vk::BufferUsageFlags,
+    memory_location: vk::MemoryPropertyFlags,
+    descriptors: Vec<(Descriptor, u32)>,
+    vust: Vust
+}
+
+impl<T: Pod> DynamicBuffer<T> {
+    pub(crate) fn new(vust: &Vust, name: String, data: Vec<T>, capacity: usize, usage: vk::BufferUsageFlags, memory_location: vk::MemoryPropertyFlags) -> Result<Self, VustError> {
+        // vulkan doesnt allow empty buffers
+        let capacity = capacity.max(data.len()).max(1);
+
+        Ok(Self {
+            buffer: Self::create_buffer(vust, &name, &data, capacity, usage, memory_location)?,
+            name,
+            data,
+            usage,
+            memory_location,
+            descriptors: Vec::new(),
+            vust: vust.clone()
+        })
+    }
+
+    fn create_buffer(vust: &Vust, name: &str, data: &[T], capacity: usize, usage: vk::BufferUsageFlags, memory_location: vk::MemoryPropertyFlags) -> Result<TypedBuffer<T>, VustError> {
+        Buffer::builder()
+            .with_name(name)
+            .with_data(data)
+            .with_capacity(capacity)
+            .with_usage(usage)
+            .with_memory_location(memory_location)
+            .build_typed(vust)
+    }
+
+    pub fn push(&mut self, value: T) -> Result<(), VustError> {
+        self.extend_from_slice(&[value])
+    }
+
+    /// Appends to the end, only the new part is written to the gpu unless the buffer grows
+    /// 
+    /// Nothing is appended if growing or writing fails
+    pub fn extend_from_slice(&mut self, data: &[T]) -> Result<(), VustError> {
+        let offset = self.data.len();
+        // the new buffer is filled from self.data, so it has to hold the new data before growing
+        self.data.extend_from_slice(data);
+
+        let result = match self.reserve_for(self.data.len()) {
+            Ok(true) => Ok(()),
+            Ok(false) => self.buffer.write_offset(data, offset),
+            Err(err) => Err(err)
+        };
+        // keep the cpu copy matching the gpu buffer
+        if result.is_err() {
+            self.data.truncate(offset);
+        }
+        result
+    }
+
+    /// Replaces the contents
+    /// 
+    /// The old contents stay if growing or writing fails
+    pub fn write(&mut self, data: &[T]) -> Result<(), VustError> {
+        let old_data = std::mem::replace(&mut self.data, data.to_vec());
+
+        let result = match self.reserve_for(self.data.len()) {
+            Ok(true) => Ok(()),
+            Ok(false) => self.buffer.write(data),
+            Err(err) => Err(err)
+        };
+        if result.is_err() {
+            self.data = old_data;
+        }
+        result
+    }
+
+    /// len goes back to 0, the capacity stays
+    pub fn clear(&mut self) -> Result<(), VustError> {
+        self.write(&[])
+    }
+
+    /// Makes sure `additional` more 'T's fit without growing
+    pub fn reserve(&mut self, additional: usize) -> Result<(), VustError> {
+        self.reserve_for(self.data.len() + additional)?;
+        Ok(())
+    }
+
+    /// Grows the buffer if `needed` doesnt fit, returns true if it did (the new buffer already has all the data)
+    fn reserve_for(&mut self, needed: usize) -> Result<bool, VustError> {
+        if needed <= self.buffer.capacity() {
+            return Ok(false);
+        }
+
+        let capacity = needed.max(self.buffer.capacity() * 2);
+        let buffer = Self::create_buffer(&self.vust, &self.name, &self.data, capacity, self.usage, self.memory_location)?;
+        // dropping the old one retires it on the render thread
+        self.buffer = buffer;
+
+        for (descriptor, binding) in &self.descriptors {
+            self.vust.update_descriptor_binding(descriptor, *binding, self.write_descriptor_info())?;
+        }
+
+        Ok(true)
+    }
+
+    /// Points the descriptor's binding at this buffer now and after every grow, the range is the whole capacity
+    pub fn track_descriptor(&mut self, descriptor: &Descriptor, binding: u32) -> Result<(), VustError> {
+        self.vust.update_descriptor_binding(descriptor, binding, self.write_descriptor_info())?;
+        self.descriptors.push((descriptor.clone(), binding));
+        Ok(())
+    }
+
+    /// Whole buffer, so the range doesnt go stale when len changes
+    pub fn write_descriptor_info(&self) -> WriteDescriptorInfo {
+        WriteDescriptorInfo::Buffer {
+            buffer: self.buffer.handle(),
+            offset: 0,
+            range: vk::WHOLE_SIZE
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Changes after every grow
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer.handle()
+    }
+
+    /// Current buffer underneath, replaced on every grow
+    pub fn buffer(&self) -> &TypedBuffer<T> {
+        &self.buffer
+    }
+}
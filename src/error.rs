@@ -0,0 +1,123 @@
+use std::fmt;
+use ash::vk;
+use gpu_allocator::AllocationError;
+
+/// Everything that can go wrong in vust
+///
+/// Errors on the render thread kill it, the error is given back by VustSyncer.sync() and every command after that returns RenderThreadDead
+#[derive(Debug)]
+pub enum VustError {
+    /// vulkan loader (vulkan-1.dll/libvulkan.so) couldnt be loaded, usually means no gpu driver
+    Loading(ash::LoadingError),
+    Vulkan(vk::Result),
+    Allocation(AllocationError),
+
+    /// no physical device passed choose_physical_device or had a graphics queue
+    NoSuitableDevice,
+    NoSuitableMemoryType,
+    /// name of what the format was needed for (depth, surface..)
+    NoSupportedFormat(&'static str),
+    MissingExtension(String),
+    /// gpu doesnt have the device feature something needs, the string is its vulkan name
+    MissingFeature(&'static str),
+    /// surface create info is None or not supported on this platform
+    UnsupportedSurface,
+
+    RenderThreadDead,
+    /// something that waits for the render thread was called on it (a staged upload or Buffer.read() from a render graph record function), it would never return
+    OnRenderThread,
+
+    /// data given was bigger than the buffer, sizes are in bytes
+    BufferOverflow {
+        data_size: u64,
+        buffer_size: u64
+    },
+    /// buffer isnt mapped, only host visible buffers can be written to from the cpu
+    BufferNotMapped,
+    /// buffer cant be copied back without TRANSFER_SRC in its usage, host visible (not cached) buffers only get it when asked for
+    BufferNotReadable,
+    EmptyTextureData,
+    /// texture data size doesnt fit the dimensions and mip levels
+    InvalidTextureData,
+    /// gpu cant sample textures in this format
+    UnsupportedTextureFormat(vk::Format),
+    /// ktx2/dds file is broken or uses something vust doesnt support, the reason is in the string
+    InvalidTextureFile(&'static str),
+    TextureLayerOutOfBounds {
+        layer: u32,
+        layers: u32
+    },
+    /// region given to Texture.update_region() doesnt fit inside the texture
+    TextureRegionOutOfBounds {
+        offset: (u32, u32),
+        extent: (u32, u32),
+        dimensions: (u32, u32)
+    },
+    /// render graph passes depend on each other, the string is the name of a pass in the cycle
+    RenderGraphCycle(String),
+    /// pass uses a transient from another render graph, the string is the pass' name
+    UnknownGraphResource(String),
+    /// pipeline was created without a descriptor set layout
+    NoDescriptorSetLayout,
+    UnsupportedLayoutTransition {
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout
+    }
+}
+
+impl fmt::Display for VustError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VustError::Loading(err) => write!(f, "failed to load vulkan: {err}"),
+            VustError::Vulkan(result) => write!(f, "vulkan error: {result}"),
+            VustError::Allocation(err) => write!(f, "gpu memory allocation failed: {err}"),
+            VustError::NoSuitableDevice => write!(f, "no supported gpu found"),
+            VustError::NoSuitableMemoryType => write!(f, "gpu has no suitable memory type"),
+            VustError::NoSupportedFormat(usage) => write!(f, "gpu has no supported {usage} format"),
+            VustError::MissingExtension(extension) => write!(f, "missing vulkan extension: {extension}"),
+            VustError::MissingFeature(feature) => write!(f, "gpu doesnt support the {feature} feature"),
+            VustError::UnsupportedSurface => write!(f, "surface create info is not supported on this platform"),
+            VustError::RenderThreadDead => write!(f, "render thread is dead"),
+            VustError::OnRenderThread => write!(f, "cant wait for the render thread on the render thread"),
+            VustError::BufferOverflow { data_size, buffer_size } => write!(f, "data size ({data_size} bytes) is bigger than buffer size ({buffer_size} bytes)"),
+            VustError::BufferNotMapped => write!(f, "buffer is not host visible"),
+            VustError::BufferNotReadable => write!(f, "buffer wasnt created with TRANSFER_SRC usage"),
+            VustError::EmptyTextureData => write!(f, "texture data is empty"),
+            VustError::InvalidTextureData => write!(f, "texture data size doesnt match its dimensions"),
+            VustError::UnsupportedTextureFormat(format) => write!(f, "gpu cant sample textures in {format:?}"),
+            VustError::InvalidTextureFile(reason) => write!(f, "invalid texture file: {reason}"),
+            VustError::TextureLayerOutOfBounds { layer, layers } => write!(f, "texture layer {layer} is out of bounds, the texture has {layers} layers"),
+            VustError::TextureRegionOutOfBounds { offset, extent, dimensions } => write!(f, "texture region at {offset:?} with extent {extent:?} doesnt fit in {dimensions:?}"),
+            VustError::RenderGraphCycle(pass) => write!(f, "render graph has a cycle through pass \"{pass}\""),
+            VustError::UnknownGraphResource(pass) => write!(f, "render graph pass \"{pass}\" uses a transient that isnt in the graph"),
+            VustError::NoDescriptorSetLayout => write!(f, "pipeline has no descriptor set layout"),
+            VustError::UnsupportedLayoutTransition { old_layout, new_layout } => write!(f, "unsupported image layout transition: {old_layout:?} -> {new_layout:?}")
+        }
+    }
+}
+
+impl std::error::Error for VustError {}
+
+impl From<vk::Result> for VustError {
+    fn from(result: vk::Result) -> Self {
+        VustError::Vulkan(result)
+    }
+}
+
+impl From<AllocationError> for VustError {
+    fn from(err: AllocationError) -> Self {
+        VustError::Allocation(err)
+    }
+}
+
+impl From<ash::LoadingError> for VustError {
+    fn from(err: ash::LoadingError) -> Self {
+        VustError::Loading(err)
+    }
+}
+
+impl<T> From<std::sync::mpsc::SendError<T>> for VustError {
+    fn from(_: std::sync::mpsc::SendError<T>) -> Self {
+        VustError::RenderThreadDead
+    }
+}
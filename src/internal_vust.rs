@@ -1,15 +1,20 @@
-use std::{collections::HashMap, ffi::{CStr, CString}, sync::{atomic::{AtomicUsize, Ordering}, mpsc, Arc, Mutex}};
+use std::{ffi::{CStr, CString}, sync::{atomic::{AtomicUsize, Ordering}, mpsc, Arc, Mutex}};
 use ash::{extensions, vk};
-use gpu_allocator::vulkan::{Allocation, Allocator, AllocatorCreateDesc};
-use crate::{create_info::{self, VustCreateInfo}, descriptor::Descriptor, pipeline::GraphicsPipeline, vust_command::{DestroyBuffer, DestroyTexture, VustCommand}, write_descriptor_info::WriteDescriptorInfo, Vust};
+use gpu_allocator::{vulkan::{AllocationCreateDesc, AllocationScheme, Allocator, AllocatorCreateDesc}, MemoryLocation};
+use crate::{capture::{self, CapturedImage, PendingCapture}, create_info::{self, LoadOp, SurfaceCreateInfo, VustCreateInfo}, error::VustError, descriptor::Descriptor, render_graph::{CompiledRenderGraph, PassContext}, sampler::SamplerBuilder, texture, vust_command::{DestroyBuffer, DestroyRenderTarget, DestroyTexture, PendingRead, ReadCallback, TextureUpdate, VustCommand}, write_descriptor_info::WriteDescriptorInfo, Vust};
 
 pub(super) struct InternalVust {
+    // the vulkan library has to stay loaded as long as the instance lives
+    #[allow(dead_code)]
     entry: ash::Entry,
     instance: ash::Instance,
 
+    // validation messages keep coming in through these until the instance is gone
     #[cfg(debug_assertions)]
+    #[allow(dead_code)]
     debug_utils_loader: extensions::ext::DebugUtils,
     #[cfg(debug_assertions)]
+    #[allow(dead_code)]
     debug_utils_messenger: vk::DebugUtilsMessengerEXT,
 
     physical_device: vk::PhysicalDevice,
@@ -18,43 +23,107 @@ pub(super) struct InternalVust {
     queue_index: u32,
     queue: vk::Queue,
 
-    surface_util: extensions::khr::Surface,
-    surface: vk::SurfaceKHR,
+    presentation: Presentation,
 
     swapchain_format: vk::SurfaceFormatKHR,
     extent: vk::Extent2D,
-    swapchain_util: extensions::khr::Swapchain,
-    swapchain: vk::SwapchainKHR,
+    /// swapchain images, or the offscreen color images when headless
+    swapchain_images: Vec<vk::Image>,
     swapchain_image_views: Vec<vk::ImageView>,
+    framebuffer_size: (u32, u32),
+    present_modes: Vec<vk::PresentModeKHR>,
+    /// set on resize or when the swapchain reports out of date/suboptimal, recreated before the next frame
+    swapchain_out_of_date: bool,
+    /// images that have been rendered to since they were created, the rest cant be loaded from
+    rendered_images: Vec<bool>,
+
+    load_op: LoadOp,
+    clear_color: [f32; 4],
+    clear_depth_stencil: vk::ClearDepthStencilValue,
 
     command_pool: vk::CommandPool,
 
+    depth_format: vk::Format,
     depth_image: vk::Image,
     depth_image_view: vk::ImageView,
     depth_image_memory: vk::DeviceMemory,
+    /// sample count of the main pass, TYPE_1 without msaa
+    samples: vk::SampleCountFlags,
+    /// multisampled color image the main pass draws into, resolved onto the swapchain image at the end of it
+    msaa_color: Option<(vk::Image, vk::DeviceMemory, vk::ImageView)>,
 
     renderpass: vk::RenderPass,
+    /// same as renderpass but loads the previous color contents
+    load_renderpass: vk::RenderPass,
     swapchain_framebuffers: Vec<vk::Framebuffer>,
 
-    draw_command_buffers: [vk::CommandBuffer; 2],
-    image_available_semaphores: [vk::Semaphore; 2],
-    render_finished_semaphores: [vk::Semaphore; 2],
-    in_flight_fences: [vk::Fence; 2],
+    frames_in_flight: usize,
+    draw_command_buffers: Vec<vk::CommandBuffer>,
+    image_available_semaphores: Vec<vk::Semaphore>,
+    render_finished_semaphores: Vec<vk::Semaphore>,
+    in_flight_fences: Vec<vk::Fence>,
     current_frame: usize,
+    /// current_frame for the Vust handles, set before the frame is synced
+    shared_current_frame: Arc<AtomicUsize>,
     image_index: u32,
 
     memory_allocator: Arc<Mutex<Allocator>>,
     destroy_buffers: Vec<(u8, DestroyBuffer)>,
-    destroy_textures: Vec<(u8, DestroyTexture)>
+    destroy_textures: Vec<(u8, DestroyTexture)>,
+
+    active_pass: ActivePass,
+    /// the main renderpass was already started this frame, starting it again has to load instead of clear
+    main_pass_started: bool,
+    /// reset_command_buffer() had nothing to draw to, recording is ignored until render_surface()
+    frame_skipped: bool,
+    /// between reset_command_buffer() and render_surface(), the current frame's fence has been waited on so its descriptor sets are free
+    frame_recording: bool,
+    destroy_render_targets: Vec<(u8, DestroyRenderTarget)>,
+    /// texture uploads for the start of the next frame
+    texture_updates: Vec<TextureUpdate>,
+    /// captures requested for the frame currently being recorded
+    capture_requests: Vec<mpsc::Sender<CapturedImage>>,
+    pending_captures: Vec<PendingCapture>,
+    /// buffers to read back at the end of the frame currently being recorded
+    read_requests: Vec<(vk::Buffer, vk::DeviceSize, ReadCallback)>,
+    pending_reads: Vec<PendingRead>,
+    /// single binding writes waiting for the frames whose descriptor set still needs them
+    descriptor_writes: Vec<(Vec<usize>, Descriptor, u32, WriteDescriptorInfo)>,
+    /// sampler cache shared with Vust, destroyed in destroy_samplers() once the gpu is idle
+    samplers: Arc<Mutex<Vec<(SamplerBuilder, vk::Sampler)>>>
+}
+
+/// Renderpass the frame's command buffer is inside of
+#[derive(PartialEq, Eq)]
+enum ActivePass {
+    None,
+    Main,
+    RenderTarget
+}
+
+/// Where the main renderpass ends up drawing to
+enum Presentation {
+    Swapchain {
+        surface_util: extensions::khr::Surface,
+        surface: vk::SurfaceKHR,
+        swapchain_util: extensions::khr::Swapchain,
+        swapchain: vk::SwapchainKHR
+    },
+    /// offscreen color images owned by vust, one per frame in flight
+    Headless {
+        image_memories: Vec<vk::DeviceMemory>
+    }
 }
 
 impl InternalVust {
-    pub fn new(mut create_info: VustCreateInfo) -> Self {
+    pub fn new(mut create_info: VustCreateInfo) -> Result<Self, VustError> {
         unsafe {
-            let entry = ash::Entry::load().unwrap();
+            let entry = ash::Entry::load()?;
             #[cfg(debug_assertions)]
             println!("Loaded ash entry");
 
+            let frames_in_flight = create_info.frames_in_flight;
+
             let instance = {
                 let app_info = vk::ApplicationInfo::builder()
                     .application_name(&create_info.app_name)
@@ -64,6 +133,25 @@ impl InternalVust {
                     .api_version(vk::make_api_version(0, 1, 3, 0))
                     .build();
 
+                // add whatever the surface needs if the user didnt already ask for it
+                for ext in create_info.surface_create_info.required_instance_extensions() {
+                    if !create_info.enabled_instance_extensions.iter().any(|enabled| enabled.as_c_str() == ext) {
+                        create_info.enabled_instance_extensions.push(ext.to_owned());
+                    }
+                }
+
+                // color spaces other than srgb nonlinear are only exposed through this extension
+                let swapchain_colorspace = CString::new("VK_EXT_swapchain_colorspace").unwrap();
+                let wants_swapchain_colorspace = create_info.surface_formats.iter().any(|surface_format| surface_format.color_space != vk::ColorSpaceKHR::SRGB_NONLINEAR);
+                let available_instance_extensions = entry.enumerate_instance_extension_properties(None)?
+                    .iter()
+                    .map(|properties| CStr::from_ptr(properties.extension_name.as_ptr()).to_owned())
+                    .collect::<Vec<_>>();
+                let has_swapchain_colorspace = available_instance_extensions.contains(&swapchain_colorspace);
+                if wants_swapchain_colorspace && has_swapchain_colorspace && !create_info.enabled_instance_extensions.contains(&swapchain_colorspace) {
+                    create_info.enabled_instance_extensions.push(swapchain_colorspace);
+                }
+
                 #[cfg(debug_assertions)] {
                     // only enable debug utils in debug build
                     create_info.enabled_instance_extensions.push(CString::new("VK_EXT_debug_utils").unwrap());
@@ -73,6 +161,10 @@ impl InternalVust {
                         println!("\t{}", ext.to_str().unwrap());
                     }
                 }
+                if let Some(missing_extension) = create_info.enabled_instance_extensions.iter().find(|ext| !available_instance_extensions.contains(ext)) {
+                    return Err(VustError::MissingExtension(missing_extension.to_string_lossy().to_string()));
+                }
+
                 let enabled_instance_extension_ptrs = create_info.enabled_instance_extensions.iter().map(|ext| ext.as_ptr()).collect::<Vec<_>>();
 
                 #[cfg(debug_assertions)]
@@ -87,7 +179,7 @@ impl InternalVust {
                     .enabled_layer_names(&enabled_layer_ptrs)
                     .build();
 
-                entry.create_instance(&instance_info, None).unwrap()
+                entry.create_instance(&instance_info, None)?
             };
             #[cfg(debug_assertions)]
             println!("created vulkan instance");
@@ -109,28 +201,28 @@ impl InternalVust {
                         vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
                     )
                     .pfn_user_callback(Some(Self::vulkan_debug_callback))
-                    .build(), None)
-                    .unwrap();
+                    .build(), None)?;
 
                 println!("created vulkan debug utils messenger");
                 (debug_utils_loader, debug_utils_messenger)
             };
 
-            let physical_devices = instance.enumerate_physical_devices().unwrap();
+            let physical_devices = instance.enumerate_physical_devices()?;
             let physical_device = physical_devices.into_iter().find(|physical_device| {
                 let properties = instance.get_physical_device_properties(*physical_device);
 
                 let physical_device_info = create_info::PhysicalDevice {
-                    name: CStr::from_ptr(properties.device_name.as_ptr()).to_str().unwrap().to_string(),
+                    name: CStr::from_ptr(properties.device_name.as_ptr()).to_string_lossy().to_string(),
                     device_type: match properties.device_type {
                         vk::PhysicalDeviceType::DISCRETE_GPU => create_info::PhysicalDeviceType::Discrete,
                         vk::PhysicalDeviceType::INTEGRATED_GPU => create_info::PhysicalDeviceType::Integrated,
+                        vk::PhysicalDeviceType::CPU => create_info::PhysicalDeviceType::Cpu,
                         _ => create_info::PhysicalDeviceType::NotSupported
                     }
                 };
 
                 (create_info.choose_physical_device)(physical_device_info)
-            }).unwrap();
+            }).ok_or(VustError::NoSuitableDevice)?;
 
             #[cfg(debug_assertions)]
             println!("using physical device: {}", CStr::from_ptr(instance.get_physical_device_properties(physical_device).device_name.as_ptr()).to_str().unwrap());
@@ -141,11 +233,20 @@ impl InternalVust {
                     .into_iter()
                     .enumerate()
                     .find(|(_, p)| p.queue_flags.contains(vk::QueueFlags::GRAPHICS))
-                    .unwrap();
+                    .ok_or(VustError::NoSuitableDevice)?;
     
-                let enabled_device_extensions = [
-                    CString::new("VK_KHR_swapchain").unwrap()
-                ];
+                // headless rendering never presents, so it doesnt need a swapchain
+                let enabled_device_extensions = if create_info.surface_create_info.is_headless() {
+                    Vec::new()
+                } else {
+                    vec![CString::new("VK_KHR_swapchain").unwrap()]
+                };
+                let available_device_extensions = instance.enumerate_device_extension_properties(physical_device)?;
+                for ext in &enabled_device_extensions {
+                    if !available_device_extensions.iter().any(|properties| CStr::from_ptr(properties.extension_name.as_ptr()) == ext.as_c_str()) {
+                        return Err(VustError::MissingExtension(ext.to_string_lossy().to_string()));
+                    }
+                }
                 let enabled_device_extension_ptrs = enabled_device_extensions.iter().map(|ext| ext.as_ptr()).collect::<Vec<_>>();
     
                 let queue_create_infos = vec![
@@ -165,7 +266,7 @@ impl InternalVust {
                         .enabled_features(&physical_device_features)
                         .build(),
                     None
-                ).unwrap();
+                )?;
 
                 let queue = device.get_device_queue(graphics_queue_family.0 as u32, 0);
 
@@ -174,85 +275,46 @@ impl InternalVust {
             #[cfg(debug_assertions)]
             println!("created vulkan logical device");
 
-            let surface_util = extensions::khr::Surface::new(&entry, &instance);
-
-            let surface;
+            let framebuffer_size = (create_info.framebuffer_size.0 as u32, create_info.framebuffer_size.1 as u32);
 
-            #[cfg(target_os = "windows")] {
-                let (hinstance, hwnd) = create_info.surface_create_info.into_win32();
-                let win32_surface_util = extensions::khr::Win32Surface::new(&entry, &instance);
+            let (presentation, swapchain_format, extent, swapchain_images) = match create_info.surface_create_info {
+                SurfaceCreateInfo::Headless { width, height, format } => {
+                    let extent = vk::Extent2D { width, height };
+                    let (images, image_memories) = Self::create_headless_images(&instance, physical_device, &device, format, extent, frames_in_flight)?;
+                    #[cfg(debug_assertions)]
+                    println!("created headless color images");
 
-                surface = win32_surface_util.create_win32_surface(
-                    &vk::Win32SurfaceCreateInfoKHR::builder()
-                        .hinstance(hinstance)
-                        .hwnd(hwnd)
-                        .build(),
-                    None
-                ).unwrap();
-
-                println!("created win32 vulkan surface");
-            }
+                    (
+                        Presentation::Headless { image_memories },
+                        vk::SurfaceFormatKHR { format, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
+                        extent,
+                        images
+                    )
+                }
+                surface_create_info => {
+                    let surface_util = extensions::khr::Surface::new(&entry, &instance);
+                    let surface = Self::create_surface(&entry, &instance, surface_create_info)?;
 
-            let swapchain_util = extensions::khr::Swapchain::new(&instance, &device);
+                    let swapchain_util = extensions::khr::Swapchain::new(&instance, &device);
 
-            let capabilities = surface_util.get_physical_device_surface_capabilities(physical_device, surface).unwrap();
-            let swapchain_format = surface_util.get_physical_device_surface_formats(physical_device, surface).unwrap().into_iter()
-                .find(|format| format.format == vk::Format::B8G8R8A8_SRGB && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
-                .unwrap();
+                    let swapchain_format = Self::choose_surface_format(&surface_util, surface, physical_device, &create_info.surface_formats)?;
 
-            let framebuffer = create_info.framebuffer_size;
+                    let extent = Self::swapchain_extent(&surface_util, surface, physical_device, framebuffer_size)?;
+                    let present_mode = Self::choose_present_mode(&surface_util, surface, physical_device, &create_info.present_modes)?;
+                    let (swapchain, images) = Self::create_swapchain(&surface_util, surface, &swapchain_util, physical_device, swapchain_format, present_mode, extent, vk::SwapchainKHR::null())?;
+                    #[cfg(debug_assertions)]
+                    println!("created vulkan swapchain");
 
-            let extent = vk::Extent2D {
-                width: (framebuffer.0 as u32).clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
-                height: (framebuffer.1 as u32).clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height)
+                    (
+                        Presentation::Swapchain { surface_util, surface, swapchain_util, swapchain },
+                        swapchain_format,
+                        extent,
+                        images
+                    )
+                }
             };
 
-            let swapchain = swapchain_util.create_swapchain(
-                &vk::SwapchainCreateInfoKHR::builder()
-                    .surface(surface)
-                    .min_image_count(capabilities.min_image_count + 1)
-                    .image_format(swapchain_format.format)
-                    .image_color_space(swapchain_format.color_space)
-                    .image_extent(extent)
-                    .image_array_layers(1)
-                    .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
-                    .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-                    .present_mode(vk::PresentModeKHR::IMMEDIATE)
-                    .pre_transform(capabilities.current_transform)
-                    .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-                    .clipped(true)
-                    .old_swapchain(vk::SwapchainKHR::null())
-                    .build(),
-                None
-            ).unwrap();
-            #[cfg(debug_assertions)]
-            println!("created vulkan swapchain");
-
-            let images = swapchain_util.get_swapchain_images(swapchain).unwrap();
-
-            let swapchain_image_views = images.iter().map(|image| {
-                device.create_image_view(
-                    &vk::ImageViewCreateInfo::builder()
-                        .image(*image)
-                        .view_type(vk::ImageViewType::TYPE_2D)
-                        .format(swapchain_format.format)
-                        .components(vk::ComponentMapping {
-                            r: vk::ComponentSwizzle::IDENTITY,
-                            g: vk::ComponentSwizzle::IDENTITY,
-                            b: vk::ComponentSwizzle::IDENTITY,
-                            a: vk::ComponentSwizzle::IDENTITY
-                        })
-                        .subresource_range(vk::ImageSubresourceRange {
-                            aspect_mask: vk::ImageAspectFlags::COLOR,
-                            base_mip_level: 0,
-                            level_count: 1,
-                            base_array_layer: 0,
-                            layer_count: 1
-                        })
-                        .build(),
-                    None
-                ).unwrap()
-            }).collect::<Vec<_>>();
+            let swapchain_image_views = Self::create_swapchain_image_views(&device, &swapchain_images, swapchain_format.format)?;
             #[cfg(debug_assertions)]
             println!("created vulkan swapchain image views");
 
@@ -262,26 +324,10 @@ impl InternalVust {
                     .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
                     .build(),
                 None
-            ).unwrap();
+            )?;
             #[cfg(debug_assertions)]
             println!("created vulkan command pool");
 
-            let color_attachment = vk::AttachmentDescription::builder()
-                .format(swapchain_format.format)
-                .samples(vk::SampleCountFlags::TYPE_1)
-                .load_op(vk::AttachmentLoadOp::CLEAR)
-                .store_op(vk::AttachmentStoreOp::STORE)
-                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-                .initial_layout(vk::ImageLayout::UNDEFINED)
-                .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-                .build();
-
-            let color_attachment_ref = vk::AttachmentReference::builder()
-                .attachment(0)
-                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                .build();
-
             let depth_format = {
                 let wanted_formats = [vk::Format::D24_UNORM_S8_UINT, vk::Format::D32_SFLOAT_S8_UINT];
                 
@@ -295,192 +341,53 @@ impl InternalVust {
                     }
                 }
 
-                return_format.expect("physical device has no supported depth format")
+                return_format.ok_or(VustError::NoSupportedFormat("depth"))?
             };
 
-            let depth_attachment = vk::AttachmentDescription::builder()
-                .format(depth_format)
-                .samples(vk::SampleCountFlags::TYPE_1)
-                .load_op(vk::AttachmentLoadOp::CLEAR)
-                .store_op(vk::AttachmentStoreOp::DONT_CARE)
-                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-                .initial_layout(vk::ImageLayout::UNDEFINED)
-                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
-                .build();
-
-            let depth_attachment_ref = vk::AttachmentReference::builder()
-                .attachment(1)
-                .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
-                .build();
+            let samples = {
+                let limits = instance.get_physical_device_properties(physical_device).limits;
+                let supported = limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
 
-            let depth_image = device.create_image(
-                &vk::ImageCreateInfo::builder()
-                    .image_type(vk::ImageType::TYPE_2D)
-                    .extent(vk::Extent3D {
-                        width: extent.width,
-                        height: extent.height,
-                        depth: 1,
-                    })
-                    .mip_levels(1)
-                    .array_layers(1)
-                    .format(depth_format)
-                    .samples(vk::SampleCountFlags::TYPE_1)
-                    .tiling(vk::ImageTiling::OPTIMAL)
-                    .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
-                    .initial_layout(vk::ImageLayout::UNDEFINED)
-                    .build(),
-                None
-            ).unwrap();
-
-            let depth_image_memory = {
-                let memory_requirements = device.get_image_memory_requirements(depth_image);
-        
-                device.allocate_memory(
-                    &vk::MemoryAllocateInfo::builder()
-                        .allocation_size(memory_requirements.size)
-                        .memory_type_index(Self::find_memory_type(instance.get_physical_device_memory_properties(physical_device), memory_requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL).unwrap())
-                        .build(),
-                    None
-                ).unwrap()
+                // highest supported count that isnt more than asked for, TYPE_1 is always supported
+                [64, 32, 16, 8, 4, 2, 1].into_iter()
+                    .map(vk::SampleCountFlags::from_raw)
+                    .find(|samples| samples.as_raw() <= create_info.msaa_samples && supported.contains(*samples))
+                    .unwrap_or(vk::SampleCountFlags::TYPE_1)
             };
+            #[cfg(debug_assertions)]
+            println!("using {} samples per pixel", samples.as_raw());
 
-            device.bind_image_memory(depth_image, depth_image_memory, 0).unwrap();
-
-            let depth_image_view = device.create_image_view(
-                &vk::ImageViewCreateInfo::builder()
-                    .image(depth_image)
-                    .view_type(vk::ImageViewType::TYPE_2D)
-                    .format(depth_format)
-                    .components(vk::ComponentMapping {
-                        r: vk::ComponentSwizzle::IDENTITY,
-                        g: vk::ComponentSwizzle::IDENTITY,
-                        b: vk::ComponentSwizzle::IDENTITY,
-                        a: vk::ComponentSwizzle::IDENTITY
-                    })
-                    .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::DEPTH,
-                        base_mip_level: 0,
-                        level_count: 1,
-                        base_array_layer: 0,
-                        layer_count: 1
-                    })
-                    .build(),
-                None
-            ).unwrap();
-
-            let transition_depth_image_command_buffer = device.allocate_command_buffers(
-                &vk::CommandBufferAllocateInfo::builder()
-                    .command_pool(command_pool)
-                    .command_buffer_count(1)
-                    .level(vk::CommandBufferLevel::PRIMARY)
-            ).unwrap()[0];
-
-            device.begin_command_buffer(
-                transition_depth_image_command_buffer,
-                &vk::CommandBufferBeginInfo::builder()
-                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
-                    .build(),
-            ).unwrap();
-
-            let barrier = vk::ImageMemoryBarrier::builder()
-                .old_layout(vk::ImageLayout::UNDEFINED)
-                .new_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
-                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-                .image(depth_image)
-                .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
-                .subresource_range(
-                    vk::ImageSubresourceRange::builder()
-                        .aspect_mask(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL)
-                        .base_mip_level(0)
-                        .level_count(1)
-                        .base_array_layer(0)
-                        .layer_count(1)
-                        .build()
-                )
-            .build();
-
-            device.cmd_pipeline_barrier(
-                transition_depth_image_command_buffer,
-                vk::PipelineStageFlags::TOP_OF_PIPE,
-                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &[barrier]
-            );
-
-            device.end_command_buffer(transition_depth_image_command_buffer).unwrap();
-
-            device.queue_submit(
-                queue,
-                &[
-                    vk::SubmitInfo::builder()
-                        .command_buffers(&[transition_depth_image_command_buffer])
-                        .build(),
-                ],
-                vk::Fence::null()
-            ).unwrap();
-
-            device.queue_wait_idle(queue).unwrap();
-
-            device.free_command_buffers(
-                command_pool,
-                &[transition_depth_image_command_buffer],
-            );
+            let (depth_image, depth_image_memory, depth_image_view) = Self::create_depth_image(&instance, physical_device, &device, command_pool, queue, depth_format, extent, samples)?;
+            let msaa_color = Self::create_msaa_color_image(&instance, physical_device, &device, swapchain_format.format, extent, samples)?;
 
-            let renderpass = device.create_render_pass(
-                &vk::RenderPassCreateInfo::builder()
-                    .attachments(&[color_attachment, depth_attachment])
-                    .subpasses(&[
-                        vk::SubpassDescription::builder()
-                            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-                            .color_attachments(&[color_attachment_ref])
-                            .depth_stencil_attachment(&depth_attachment_ref)
-                            .build()
-                    ])
-                    .dependencies(&[
-                        vk::SubpassDependency::builder()
-                            .src_subpass(vk::SUBPASS_EXTERNAL)
-                            .dst_subpass(0)
-                            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
-                            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
-                            .src_access_mask(vk::AccessFlags::empty())
-                            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
-                            .build()
-                    ]),
-                None
-            ).unwrap();
+            let color_final_layout = match &presentation {
+                Presentation::Swapchain { .. } => vk::ImageLayout::PRESENT_SRC_KHR,
+                // ready to be copied out by the user
+                Presentation::Headless { .. } => vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+            };
+            // only the load ops differ, so pipelines and framebuffers work with both
+            let renderpass = Self::create_renderpass(&device, swapchain_format.format, color_final_layout, depth_format, samples, LoadOp::Clear)?;
+            let load_renderpass = Self::create_renderpass(&device, swapchain_format.format, color_final_layout, depth_format, samples, LoadOp::Load)?;
+            let rendered_images = vec![false; swapchain_images.len()];
 
-            let swapchain_framebuffers = swapchain_image_views.iter().map(|image_view| {
-                let attachments = [*image_view, depth_image_view];
-                device.create_framebuffer(&vk::FramebufferCreateInfo::builder()
-                    .render_pass(renderpass)
-                    .attachments(&attachments)
-                    .width(extent.width)
-                    .height(extent.height)
-                    .layers(1)
-                    .build(), None).unwrap()
-            }).collect::<Vec<_>>();
+            let swapchain_framebuffers = Self::create_framebuffers(&device, renderpass, &swapchain_image_views, depth_image_view, msaa_color.map(|msaa_color| msaa_color.2), extent)?;
             #[cfg(debug_assertions)]
             println!("created swapchain framebuffers");
 
-            let draw_command_buffers: [vk::CommandBuffer; 2] = device.allocate_command_buffers(
+            let draw_command_buffers = device.allocate_command_buffers(
                 &vk::CommandBufferAllocateInfo::builder()
                     .command_pool(command_pool)
                     .level(vk::CommandBufferLevel::PRIMARY)
-                    .command_buffer_count(2)
+                    .command_buffer_count(frames_in_flight as u32)
                     .build()
-            ).unwrap().try_into().unwrap();
+            )?;
 
             let semaphore_create_info = vk::SemaphoreCreateInfo::builder().build();
             let fence_create_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED).build();
 
-            let image_available_semaphores = [device.create_semaphore(&semaphore_create_info, None).unwrap(), device.create_semaphore(&semaphore_create_info, None).unwrap()];
-            let render_finished_semaphores = [device.create_semaphore(&semaphore_create_info, None).unwrap(), device.create_semaphore(&semaphore_create_info, None).unwrap()];
-            let in_flight_fences = [device.create_fence(&fence_create_info, None).unwrap(), device.create_fence(&fence_create_info, None).unwrap()];
+            let image_available_semaphores = (0..frames_in_flight).map(|_| device.create_semaphore(&semaphore_create_info, None)).collect::<Result<Vec<_>, _>>()?;
+            let render_finished_semaphores = (0..frames_in_flight).map(|_| device.create_semaphore(&semaphore_create_info, None)).collect::<Result<Vec<_>, _>>()?;
+            let in_flight_fences = (0..frames_in_flight).map(|_| device.create_fence(&fence_create_info, None)).collect::<Result<Vec<_>, _>>()?;
 
             let memory_allocator = Allocator::new(&AllocatorCreateDesc {
                 instance: instance.clone(),
@@ -489,9 +396,9 @@ impl InternalVust {
                 debug_settings: Default::default(),
                 buffer_device_address: false,
                 allocation_sizes: Default::default()
-            }).unwrap();
+            })?;
             
-            Self {
+            Ok(Self {
                 entry,
                 instance,
             
@@ -506,120 +413,407 @@ impl InternalVust {
                 queue_index,
                 queue,
             
-                surface_util,
-                surface,
+                presentation,
             
                 swapchain_format,
                 extent,
-                swapchain_util,
-                swapchain,
+                swapchain_images,
                 swapchain_image_views,
+                framebuffer_size: (extent.width, extent.height),
+                present_modes: create_info.present_modes,
+                swapchain_out_of_date: false,
+                rendered_images,
+
+                load_op: create_info.load_op,
+                clear_color: [0.0, 0.0, 0.0, 1.0],
+                clear_depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
             
                 command_pool,
             
+                depth_format,
                 depth_image,
                 depth_image_memory,
                 depth_image_view,
+                samples,
+                msaa_color,
             
                 renderpass,
+                load_renderpass,
                 swapchain_framebuffers,
             
+                frames_in_flight,
                 draw_command_buffers,
                 image_available_semaphores,
                 render_finished_semaphores,
                 in_flight_fences,
                 current_frame: 0,
+                shared_current_frame: Arc::new(AtomicUsize::new(0)),
                 image_index: 0,
             
                 memory_allocator: Arc::new(Mutex::new(memory_allocator)),
                 destroy_buffers: Vec::new(),
-                destroy_textures: Vec::new()
-            }
+                destroy_textures: Vec::new(),
+                active_pass: ActivePass::None,
+                main_pass_started: false,
+                frame_skipped: false,
+                frame_recording: false,
+                destroy_render_targets: Vec::new(),
+                texture_updates: Vec::new(),
+                capture_requests: Vec::new(),
+                pending_captures: Vec::new(),
+                read_requests: Vec::new(),
+                pending_reads: Vec::new(),
+                descriptor_writes: Vec::new(),
+                samplers: Arc::new(Mutex::new(Vec::new()))
+            })
         }
     }
 
-    pub fn run(&mut self, command: VustCommand, vust_sync_sender: &mpsc::Sender<()>) {
+    pub fn run(&mut self, command: VustCommand, vust_sync_sender: &mpsc::Sender<Result<bool, VustError>>) -> Result<(), VustError> {
+        // no command buffer was begun for a skipped frame, drop everything that would be recorded into it
+        if self.frame_skipped {
+            match command {
+                VustCommand::RenderSurface => {
+                    self.frame_skipped = false;
+                    return Ok(());
+                }
+                VustCommand::BindPipeline { .. } | VustCommand::BindViewport { .. } | VustCommand::BindScissor { .. } | VustCommand::SetStencilReference { .. } |
+                VustCommand::BindDescriptorSet { .. } | VustCommand::BindVertexBuffer { .. } | VustCommand::BindIndexBuffer { .. } |
+                VustCommand::Draw { .. } | VustCommand::DrawIndexed { .. } |
+                VustCommand::BeginRenderTarget { .. } | VustCommand::EndRenderTarget | VustCommand::ExecuteRenderGraph { .. } => return Ok(()),
+                _ => {}
+            }
+        }
+
         match command {
             VustCommand::KYS => { /* handled outside this function */ },
 
             VustCommand::DestroyBuffer { buffer, allocation } => {
                 self.destroy_buffers.push((0, DestroyBuffer { buffer, allocation }));
             },
-            VustCommand::DestroyTexture { image, view, sampler, allocation } => {
-                self.destroy_textures.push((0, DestroyTexture { image, view, sampler, allocation }));
+            VustCommand::DestroyTexture { image, view, allocation } => {
+                self.destroy_textures.push((0, DestroyTexture { image, view, allocation }));
             },
+            VustCommand::DestroyRenderTarget { renderpass, framebuffer } => {
+                self.destroy_render_targets.push((0, DestroyRenderTarget { renderpass, framebuffer }));
+            },
+
+            VustCommand::Resize { width, height } => {
+                // recreated at the start of the next frame, cant touch the framebuffers while recording
+                self.framebuffer_size = (width, height);
+                self.swapchain_out_of_date = true;
+            },
+            VustCommand::SetVsync { vsync } => {
+                self.present_modes = if vsync {
+                    vec![vk::PresentModeKHR::FIFO]
+                } else {
+                    vec![vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+                };
+                self.swapchain_out_of_date = true;
+            },
+
+            VustCommand::SetClearColor { color } => self.clear_color = color,
+            VustCommand::SetClearDepthStencil { depth, stencil } => self.clear_depth_stencil = vk::ClearDepthStencilValue { depth, stencil },
+            VustCommand::SetLoadOp { load_op } => self.load_op = load_op,
+
+            VustCommand::ReadBuffer { buffer, size, callback } => self.read_requests.push((buffer, size, callback)),
+            VustCommand::ReadBufferNow { buffer, size, callback } => unsafe { self.read_buffer_now(buffer, size, callback)? },
+            VustCommand::UpdateTexture { staging_buffer, image, layer, offset, extent } => self.texture_updates.push(TextureUpdate { staging_buffer, image, layer, offset, extent }),
+            VustCommand::SubmitSingleExec { command_buffer, fence, sender } => {
+                let result = unsafe { self.device.queue_submit(self.queue, &[vk::SubmitInfo::builder().command_buffers(&[command_buffer]).build()], fence) };
+                // a failed submit is the caller's problem, not the render thread's
+                let _ = sender.send(result.map_err(VustError::from));
+            },
+            VustCommand::CaptureFrame { sender } => self.capture_requests.push(sender),
 
             VustCommand::ResetCommandBuffer => {
-                self.reset_command_buffer();
-                vust_sync_sender.send(()).unwrap();
+                let rendering = self.reset_command_buffer()?;
+                // syncer being dropped just means the user doesnt care about syncing
+                let _ = vust_sync_sender.send(Ok(rendering));
             },
             VustCommand::BindPipeline { pipeline_handle } => self.bind_pipeline(pipeline_handle),
             VustCommand::BindViewport { viewport } => self.bind_viewport(viewport),
             VustCommand::BindScissor { scissor } => self.bind_scissor(scissor),
-            VustCommand::BindDescriptorSet { pipeline_layout, descriptor } => self.bind_descriptor_set(pipeline_layout, &descriptor),
+            VustCommand::SetStencilReference { face_mask, reference } => self.set_stencil_reference(face_mask, reference),
+            VustCommand::BindDescriptorSet { pipeline_layout, descriptor, dynamic_offsets } => self.bind_descriptor_set(pipeline_layout, &descriptor, &dynamic_offsets),
             VustCommand::BindVertexBuffer { vertex_buffer } => self.bind_vertex_buffer(vertex_buffer),
             VustCommand::BindIndexBuffer { index_buffer } => self.bind_index_buffer(index_buffer),
             VustCommand::Draw { vertex_count } => self.draw(vertex_count),
             VustCommand::DrawIndexed { index_count } => self.draw_indexed(index_count),
+            VustCommand::BeginRenderTarget { renderpass, framebuffer, extent, clear_values } => self.begin_render_target(renderpass, framebuffer, extent, &clear_values),
+            VustCommand::EndRenderTarget => self.end_render_target(),
+            VustCommand::ExecuteRenderGraph { render_graph } => self.execute_render_graph(&render_graph),
             VustCommand::UpdateDescriptorSet { descriptor, write_descriptor_infos } => self.update_descriptor_set(&descriptor, &write_descriptor_infos),
-            VustCommand::RenderSurface => self.render_surface()
+            VustCommand::UpdateDescriptorBinding { descriptor, binding, write_descriptor_info } => {
+                // the frame being recorded cant wait for its next start, its set isnt used by the gpu anymore so it gets written now
+                let mut frames = (0..self.frames_in_flight).collect::<Vec<_>>();
+                if self.frame_recording {
+                    self.write_descriptor_binding(&descriptor, binding, write_descriptor_info, self.current_frame);
+                    frames.retain(|frame| *frame != self.current_frame);
+                }
+
+                // every other frame's set gets it at the start of that frame, before anything can be drawn with it
+                if !frames.is_empty() {
+                    self.descriptor_writes.push((frames, descriptor, binding, write_descriptor_info));
+                }
+            },
+            VustCommand::RenderSurface => self.render_surface()?
         }
+
+        Ok(())
     }
 
-    pub fn reset_command_buffer(&mut self) {
+    /// Returns false if the frame is skipped because there is nothing to draw to (minimized window), recording commands are ignored until render_surface()
+    pub fn reset_command_buffer(&mut self) -> Result<bool, VustError> {
         unsafe {
-            self.device.wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, std::u64::MAX).unwrap();
-            self.device.reset_fences(&[self.in_flight_fences[self.current_frame]]).unwrap();
+            self.device.wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, u64::MAX)?;
 
-            self.device.reset_command_buffer(self.draw_command_buffers[self.current_frame], vk::CommandBufferResetFlags::empty()).unwrap();
+            // skipping has to happen before the fence is reset and anything is counted as a frame,
+            // a Resize command can still come in and give the window a size again
+            if self.swapchain_out_of_date && !self.recreate_swapchain()? {
+                self.frame_skipped = true;
+                return Ok(false);
+            }
+
+            let acquired = match &self.presentation {
+                Presentation::Swapchain { swapchain_util, swapchain, .. } => {
+                    swapchain_util.acquire_next_image(
+                        *swapchain,
+                        u64::MAX,
+                        self.image_available_semaphores[self.current_frame],
+                        vk::Fence::null()
+                    )
+                }
+                // one offscreen image per frame in flight, nothing to acquire
+                Presentation::Headless { .. } => Ok((self.current_frame as u32, false))
+            };
+
+            self.image_index = match acquired {
+                Ok((image_index, suboptimal)) => {
+                    // still usable, recreate after this frame
+                    self.swapchain_out_of_date |= suboptimal;
+                    image_index
+                }
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    // nothing was signaled, recreated at the start of the next frame
+                    self.swapchain_out_of_date = true;
+                    self.frame_skipped = true;
+                    return Ok(false);
+                }
+                Err(err) => return Err(err.into())
+            };
+
+            self.device.reset_fences(&[self.in_flight_fences[self.current_frame]])?;
+            self.shared_current_frame.store(self.current_frame, Ordering::Release);
+
+            // this frame's previous submission is done, its captures and reads can be read
+            self.finish_captures(Some(self.current_frame))?;
+            self.finish_reads(Some(self.current_frame))?;
+
+            self.device.reset_command_buffer(self.draw_command_buffers[self.current_frame], vk::CommandBufferResetFlags::empty())?;
+
+            // this frame's descriptor sets arent used by the gpu anymore
+            let descriptor_writes = std::mem::take(&mut self.descriptor_writes);
+            for (mut frames, descriptor, binding, write_descriptor_info) in descriptor_writes {
+                if let Some(index) = frames.iter().position(|frame| *frame == self.current_frame) {
+                    self.write_descriptor_binding(&descriptor, binding, write_descriptor_info, self.current_frame);
+                    frames.remove(index);
+                }
+
+                if !frames.is_empty() {
+                    self.descriptor_writes.push((frames, descriptor, binding, write_descriptor_info));
+                }
+            }
 
             // destroy memory
-            let mut memory_allocator = self.memory_allocator.lock().unwrap();
-            let destroy_buffers = std::mem::take(&mut self.destroy_buffers);
-            let destroy_textures = std::mem::take(&mut self.destroy_textures);
-            // skip 3 frames before freeing the memory, the buffer shouldnt be used in rendering after 3 frames
-            for (frame, destroy_buffer) in destroy_buffers {
-                if frame == 3 {
-                    memory_allocator.free(destroy_buffer.allocation).unwrap();
-                } else {
-                    self.destroy_buffers.push((frame + 1, destroy_buffer));
+            {
+                let mut memory_allocator = self.memory_allocator.lock().unwrap();
+                let destroy_buffers = std::mem::take(&mut self.destroy_buffers);
+                let destroy_textures = std::mem::take(&mut self.destroy_textures);
+                // skip frames_in_flight + 1 frames before freeing the memory, every frame and descriptor set that could use it has been replaced by then
+                for (frame, destroy_buffer) in destroy_buffers {
+                    if frame as usize > self.frames_in_flight {
+                        self.device.destroy_buffer(destroy_buffer.buffer, None);
+                        memory_allocator.free(destroy_buffer.allocation)?;
+                    } else {
+                        self.destroy_buffers.push((frame + 1, destroy_buffer));
+                    }
+                }
+
+                for (frame, destroy_texture) in destroy_textures {
+                    if frame as usize > self.frames_in_flight {
+                        self.device.destroy_image_view(destroy_texture.view, None);
+                        self.device.destroy_image(destroy_texture.image, None);
+                        memory_allocator.free(destroy_texture.allocation)?;
+                    } else {
+                        self.destroy_textures.push((frame + 1, destroy_texture));
+                    }
                 }
             }
 
-            for (frame, destroy_texture) in destroy_textures {
-                if frame == 3 {
-                    memory_allocator.free(destroy_texture.allocation).unwrap();
+            for (frame, destroy_render_target) in std::mem::take(&mut self.destroy_render_targets) {
+                if frame as usize > self.frames_in_flight {
+                    self.device.destroy_framebuffer(destroy_render_target.framebuffer, None);
+                    self.device.destroy_render_pass(destroy_render_target.renderpass, None);
                 } else {
-                    self.destroy_textures.push((frame + 1, destroy_texture));
+                    self.destroy_render_targets.push((frame + 1, destroy_render_target));
                 }
             }
 
-            self.image_index = self.swapchain_util.acquire_next_image(
-                self.swapchain,
-                std::u64::MAX,
-                self.image_available_semaphores[self.current_frame],
-                vk::Fence::null()
-            ).unwrap().0;
+            self.device.begin_command_buffer(self.draw_command_buffers[self.current_frame], &vk::CommandBufferBeginInfo::builder().build())?;
+            // copies cant be recorded inside the renderpass
+            self.record_texture_updates();
 
-            self.device.begin_command_buffer(self.draw_command_buffers[self.current_frame], &vk::CommandBufferBeginInfo::builder().build()).unwrap();
+            // render targets can still be drawn before the main renderpass starts
+            self.active_pass = ActivePass::None;
+            self.main_pass_started = false;
+            self.frame_recording = true;
+        }
+
+        Ok(true)
+    }
+
+    /// Starts the main renderpass, or continues it with the load renderpass if a render target interrupted it
+    fn begin_main_pass(&mut self) {
+        unsafe {
+            // a new image has nothing to load yet
+            let renderpass = match self.load_op {
+                _ if self.main_pass_started => self.load_renderpass,
+                LoadOp::Load if self.rendered_images[self.image_index as usize] => self.load_renderpass,
+                _ => self.renderpass
+            };
+            // the load renderpass would bring back the last frame's depth and stencil
+            let clear_depth = renderpass == self.load_renderpass && !self.main_pass_started;
+            self.rendered_images[self.image_index as usize] = true;
+            self.main_pass_started = true;
+            self.active_pass = ActivePass::Main;
 
             self.device.cmd_begin_render_pass(
                 self.draw_command_buffers[self.current_frame],
                 &vk::RenderPassBeginInfo::builder()
-                    .render_pass(self.renderpass)
+                    .render_pass(renderpass)
                     .framebuffer(self.swapchain_framebuffers[self.image_index as usize])
                     .render_area(vk::Rect2D {
                         offset: vk::Offset2D { x: 0, y: 0 },
                         extent: self.extent
                     })
                     .clear_values(&[vk::ClearValue {
-                        color: vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 1.0] }
+                        color: vk::ClearColorValue { float32: self.clear_color }
                     }, vk::ClearValue {
-                        depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 }
+                        depth_stencil: self.clear_depth_stencil
                     }])
                     .build(),
                 vk::SubpassContents::INLINE
             );
+
+            if clear_depth {
+                self.device.cmd_clear_attachments(
+                    self.draw_command_buffers[self.current_frame],
+                    &[vk::ClearAttachment {
+                        aspect_mask: vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+                        color_attachment: 0,
+                        clear_value: vk::ClearValue { depth_stencil: self.clear_depth_stencil }
+                    }],
+                    &[vk::ClearRect {
+                        rect: vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: self.extent },
+                        base_array_layer: 0,
+                        layer_count: 1
+                    }]
+                );
+            }
+        }
+    }
+
+    /// Draws go here until end_render_target(), the main renderpass is ended first if it was already going
+    pub fn begin_render_target(&mut self, renderpass: vk::RenderPass, framebuffer: vk::Framebuffer, extent: vk::Extent2D, clear_values: &[vk::ClearValue]) {
+        unsafe {
+            if self.active_pass != ActivePass::None {
+                self.device.cmd_end_render_pass(self.draw_command_buffers[self.current_frame]);
+            }
+
+            self.device.cmd_begin_render_pass(
+                self.draw_command_buffers[self.current_frame],
+                &vk::RenderPassBeginInfo::builder()
+                    .render_pass(renderpass)
+                    .framebuffer(framebuffer)
+                    .render_area(vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent
+                    })
+                    .clear_values(clear_values)
+                    .build(),
+                vk::SubpassContents::INLINE
+            );
+        }
+
+        self.active_pass = ActivePass::RenderTarget;
+    }
+
+    pub fn end_render_target(&mut self) {
+        if self.active_pass == ActivePass::RenderTarget {
+            unsafe {
+                self.device.cmd_end_render_pass(self.draw_command_buffers[self.current_frame]);
+            }
+            self.active_pass = ActivePass::None;
+        }
+    }
+
+    /// Records every pass in order, surface passes draw into the main renderpass which stays open for render_surface()
+    pub fn execute_render_graph(&mut self, render_graph: &CompiledRenderGraph) {
+        for pass in render_graph.passes.iter() {
+            if !pass.barriers.is_empty() {
+                // barriers cant go inside a renderpass without a self dependency
+                if self.active_pass != ActivePass::None {
+                    unsafe {
+                        self.device.cmd_end_render_pass(self.draw_command_buffers[self.current_frame]);
+                    }
+                    self.active_pass = ActivePass::None;
+                }
+
+                let buffer_barriers = pass.barriers.iter().map(|barrier| {
+                    let (_, _, src_access, dst_access) = barrier.masks();
+                    vk::BufferMemoryBarrier::builder()
+                        .buffer(barrier.buffer)
+                        .offset(0)
+                        .size(vk::WHOLE_SIZE)
+                        .src_access_mask(src_access)
+                        .dst_access_mask(dst_access)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .build()
+                }).collect::<Vec<_>>();
+                let (src_stage, dst_stage) = pass.barriers.iter().fold((vk::PipelineStageFlags::empty(), vk::PipelineStageFlags::empty()), |(src_stages, dst_stages), barrier| {
+                    let (src_stage, dst_stage, _, _) = barrier.masks();
+                    (src_stages | src_stage, dst_stages | dst_stage)
+                });
+
+                unsafe {
+                    self.device.cmd_pipeline_barrier(
+                        self.draw_command_buffers[self.current_frame],
+                        src_stage,
+                        dst_stage,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &buffer_barriers,
+                        &[]
+                    );
+                }
+            }
+
+            match &pass.target {
+                Some(target_info) => self.begin_render_target(target_info.renderpass, target_info.framebuffer, target_info.extent, &target_info.clear_values),
+                None if self.active_pass == ActivePass::Main => {},
+                None => {
+                    self.end_render_target();
+                    self.begin_main_pass();
+                }
+            }
+
+            if let Some(record) = pass.record.lock().unwrap().as_mut() {
+                record(&mut PassContext { internal_vust: self });
+            }
+
+            self.end_render_target();
         }
     }
 
@@ -653,7 +847,17 @@ impl InternalVust {
         }
     }
 
-    pub fn bind_descriptor_set(&self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor) {
+    pub fn set_stencil_reference(&self, face_mask: vk::StencilFaceFlags, reference: u32) {
+        unsafe {
+            self.device.cmd_set_stencil_reference(
+                self.draw_command_buffers[self.current_frame],
+                face_mask,
+                reference
+            );
+        }
+    }
+
+    pub fn bind_descriptor_set(&self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor, dynamic_offsets: &[u32]) {
         unsafe {
             self.device.cmd_bind_descriptor_sets(
                 self.draw_command_buffers[self.current_frame],
@@ -661,7 +865,7 @@ impl InternalVust {
                 pipeline_layout,
                 0,
                 &[descriptor.descriptor_set[self.current_frame]],
-                &[]
+                dynamic_offsets
             );
         }
     }
@@ -689,7 +893,11 @@ impl InternalVust {
         }
     }
 
-    pub fn draw(&self, vertex_count: u32) {
+    pub fn draw(&mut self, vertex_count: u32) {
+        if self.active_pass == ActivePass::None {
+            self.begin_main_pass();
+        }
+
         unsafe {
             self.device.cmd_draw(
                 self.draw_command_buffers[self.current_frame],
@@ -701,7 +909,11 @@ impl InternalVust {
         }
     }
 
-    pub fn draw_indexed(&self, index_count: u32) {
+    pub fn draw_indexed(&mut self, index_count: u32) {
+        if self.active_pass == ActivePass::None {
+            self.begin_main_pass();
+        }
+
         unsafe {
             self.device.cmd_draw_indexed(
                 self.draw_command_buffers[self.current_frame],
@@ -714,35 +926,436 @@ impl InternalVust {
         }
     }
 
-    pub fn render_surface(&mut self) {
+    pub fn render_surface(&mut self) -> Result<(), VustError> {
+        // the main renderpass always runs, even with nothing drawn it clears and transitions the image
+        self.end_render_target();
+        if self.active_pass == ActivePass::None {
+            self.begin_main_pass();
+        }
+
         unsafe {
             self.device.cmd_end_render_pass(self.draw_command_buffers[self.current_frame]);
-            self.device.end_command_buffer(self.draw_command_buffers[self.current_frame]).unwrap();
-
-            self.device.queue_submit(
-                self.queue,
-                &[
-                    vk::SubmitInfo::builder()
-                        .command_buffers(&[self.draw_command_buffers[self.current_frame]])
-                        .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
-                        .wait_semaphores(&[self.image_available_semaphores[self.current_frame]])
-                        .signal_semaphores(&[self.render_finished_semaphores[self.current_frame]])
-                        .build()
-                ],
-                self.in_flight_fences[self.current_frame]
-            ).unwrap();
-
-            self.swapchain_util.queue_present(
-                self.queue,
-                &vk::PresentInfoKHR::builder()
-                    .swapchains(&[self.swapchain])
-                    .image_indices(&[self.image_index])
-                    .wait_semaphores(&[self.render_finished_semaphores[self.current_frame]])
-                    .build()
-            ).unwrap();
+            self.active_pass = ActivePass::None;
+            if !self.capture_requests.is_empty() {
+                self.record_capture()?;
+            }
+            if !self.read_requests.is_empty() {
+                let read_requests = std::mem::take(&mut self.read_requests);
+                let pending_reads = self.record_reads(self.draw_command_buffers[self.current_frame], read_requests)?;
+                self.pending_reads.extend(pending_reads);
+            }
+            self.device.end_command_buffer(self.draw_command_buffers[self.current_frame])?;
+
+            match &self.presentation {
+                Presentation::Swapchain { swapchain_util, swapchain, .. } => {
+                    self.device.queue_submit(
+                        self.queue,
+                        &[
+                            vk::SubmitInfo::builder()
+                                .command_buffers(&[self.draw_command_buffers[self.current_frame]])
+                                .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+                                .wait_semaphores(&[self.image_available_semaphores[self.current_frame]])
+                                .signal_semaphores(&[self.render_finished_semaphores[self.current_frame]])
+                                .build()
+                        ],
+                        self.in_flight_fences[self.current_frame]
+                    )?;
+
+                    let present_result = swapchain_util.queue_present(
+                        self.queue,
+                        &vk::PresentInfoKHR::builder()
+                            .swapchains(&[*swapchain])
+                            .image_indices(&[self.image_index])
+                            .wait_semaphores(&[self.render_finished_semaphores[self.current_frame]])
+                            .build()
+                    );
+
+                    match present_result {
+                        Ok(suboptimal) => self.swapchain_out_of_date |= suboptimal,
+                        Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => self.swapchain_out_of_date = true,
+                        Err(err) => return Err(err.into())
+                    }
+                }
+                Presentation::Headless { .. } => {
+                    // nothing to wait on or present, the fence is enough
+                    self.device.queue_submit(
+                        self.queue,
+                        &[
+                            vk::SubmitInfo::builder()
+                                .command_buffers(&[self.draw_command_buffers[self.current_frame]])
+                                .build()
+                        ],
+                        self.in_flight_fences[self.current_frame]
+                    )?;
+                }
+            }
+
+            self.frame_recording = false;
+            self.current_frame = (self.current_frame + 1) % self.frames_in_flight;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the swapchain (or headless images), image views, depth (and msaa color) image and framebuffers for the current framebuffer size
+    /// 
+    /// Returns false if the surface currently has no size (minimized window), in which case nothing is recreated
+    pub fn recreate_swapchain(&mut self) -> Result<bool, VustError> {
+        unsafe {
+            let extent = match &self.presentation {
+                Presentation::Swapchain { surface_util, surface, .. } => Self::swapchain_extent(surface_util, *surface, self.physical_device, self.framebuffer_size)?,
+                Presentation::Headless { .. } => vk::Extent2D { width: self.framebuffer_size.0, height: self.framebuffer_size.1 }
+            };
+
+            if extent.width == 0 || extent.height == 0 {
+                return Ok(false);
+            }
+
+            self.device.device_wait_idle()?;
+
+            for framebuffer in self.swapchain_framebuffers.drain(..) {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+            for image_view in self.swapchain_image_views.drain(..) {
+                self.device.destroy_image_view(image_view, None);
+            }
+            self.device.destroy_image_view(self.depth_image_view, None);
+            self.device.destroy_image(self.depth_image, None);
+            self.device.free_memory(self.depth_image_memory, None);
+            if let Some((image, image_memory, image_view)) = self.msaa_color.take() {
+                self.device.destroy_image_view(image_view, None);
+                self.device.destroy_image(image, None);
+                self.device.free_memory(image_memory, None);
+            }
+
+            match &mut self.presentation {
+                Presentation::Swapchain { surface_util, surface, swapchain_util, swapchain } => {
+                    let old_swapchain = *swapchain;
+                    let present_mode = Self::choose_present_mode(surface_util, *surface, self.physical_device, &self.present_modes)?;
+                    let (new_swapchain, images) = Self::create_swapchain(surface_util, *surface, swapchain_util, self.physical_device, self.swapchain_format, present_mode, extent, old_swapchain)?;
+                    swapchain_util.destroy_swapchain(old_swapchain, None);
+
+                    *swapchain = new_swapchain;
+                    self.swapchain_images = images;
+                }
+                Presentation::Headless { image_memories } => {
+                    for (image, image_memory) in self.swapchain_images.drain(..).zip(image_memories.drain(..)) {
+                        self.device.destroy_image(image, None);
+                        self.device.free_memory(image_memory, None);
+                    }
+
+                    let (images, new_image_memories) = Self::create_headless_images(&self.instance, self.physical_device, &self.device, self.swapchain_format.format, extent, self.frames_in_flight)?;
+                    *image_memories = new_image_memories;
+                    self.swapchain_images = images;
+                }
+            }
+            self.extent = extent;
+            self.rendered_images = vec![false; self.swapchain_images.len()];
+
+            self.swapchain_image_views = Self::create_swapchain_image_views(&self.device, &self.swapchain_images, self.swapchain_format.format)?;
+            (self.depth_image, self.depth_image_memory, self.depth_image_view) = Self::create_depth_image(&self.instance, self.physical_device, &self.device, self.command_pool, self.queue, self.depth_format, extent, self.samples)?;
+            self.msaa_color = Self::create_msaa_color_image(&self.instance, self.physical_device, &self.device, self.swapchain_format.format, extent, self.samples)?;
+            self.swapchain_framebuffers = Self::create_framebuffers(&self.device, self.renderpass, &self.swapchain_image_views, self.depth_image_view, self.msaa_color.map(|msaa_color| msaa_color.2), extent)?;
+
+            self.swapchain_out_of_date = false;
+            #[cfg(debug_assertions)]
+            println!("recreated swapchain with extent {}x{}", extent.width, extent.height);
+
+            Ok(true)
+        }
+    }
+
+    /// Copies the image that was just rendered into a host visible buffer, read back in finish_captures once the frame's fence is signaled
+    unsafe fn record_capture(&mut self) -> Result<(), VustError> {
+        let senders = std::mem::take(&mut self.capture_requests);
+        let format = self.swapchain_format.format;
+
+        let Some(bytes_per_pixel) = capture::bytes_per_pixel(format) else {
+            #[cfg(debug_assertions)]
+            println!("cant capture frame, unsupported format {:?}", format);
+
+            // dropping the senders lets the receivers know
+            return Ok(());
+        };
+
+        let command_buffer = self.draw_command_buffers[self.current_frame];
+        let image = self.swapchain_images[self.image_index as usize];
+        let final_layout = match self.presentation {
+            Presentation::Swapchain { .. } => vk::ImageLayout::PRESENT_SRC_KHR,
+            Presentation::Headless { .. } => vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        };
+
+        let buffer = self.device.create_buffer(
+            &vk::BufferCreateInfo::builder()
+                .size(self.extent.width as u64 * self.extent.height as u64 * bytes_per_pixel)
+                .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .build(),
+            None
+        )?;
+
+        let allocation = self.memory_allocator.lock().unwrap().allocate(&AllocationCreateDesc {
+            name: "frame capture",
+            requirements: self.device.get_buffer_memory_requirements(buffer),
+            location: MemoryLocation::GpuToCpu,
+            linear: true,
+            allocation_scheme: AllocationScheme::GpuAllocatorManaged
+        })?;
+        self.device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())?;
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[vk::ImageMemoryBarrier::builder()
+                .old_layout(final_layout)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .build()]
+        );
+
+        self.device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            buffer,
+            &[vk::BufferImageCopy::builder()
+                .buffer_offset(0)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build())
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D { width: self.extent.width, height: self.extent.height, depth: 1 })
+                .build()]
+        );
+
+        // back to whatever the renderpass left it in, and make the copy visible to the cpu
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[vk::BufferMemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(buffer)
+                .offset(0)
+                .size(vk::WHOLE_SIZE)
+                .build()],
+            &[vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .new_layout(final_layout)
+                .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .dst_access_mask(vk::AccessFlags::empty())
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .build()]
+        );
+
+        self.pending_captures.push(PendingCapture {
+            frame: self.current_frame,
+            buffer,
+            allocation,
+            extent: self.extent,
+            format,
+            senders
+        });
+
+        Ok(())
+    }
+
+    /// Sends out the captures of the given frame (all captures if None), the frame's fence has to be signaled already
+    fn finish_captures(&mut self, frame: Option<usize>) -> Result<(), VustError> {
+        let pending_captures = std::mem::take(&mut self.pending_captures);
+
+        for pending_capture in pending_captures {
+            if frame.is_some_and(|frame| frame != pending_capture.frame) {
+                self.pending_captures.push(pending_capture);
+                continue;
+            }
+
+            let size = pending_capture.extent.width as usize * pending_capture.extent.height as usize * capture::bytes_per_pixel(pending_capture.format).unwrap_or(4) as usize;
+            // GpuToCpu is always mapped
+            let data = unsafe { std::slice::from_raw_parts(pending_capture.allocation.mapped_ptr().ok_or(VustError::BufferNotMapped)?.as_ptr().cast::<u8>(), size) };
+
+            let captured_image = CapturedImage {
+                pixels: capture::to_rgba8(pending_capture.format, data),
+                extent: pending_capture.extent,
+                format: pending_capture.format
+            };
+
+            for sender in pending_capture.senders {
+                // receiver being dropped just means nobody wants the image anymore
+                let _ = sender.send(captured_image.clone());
+            }
+
+            unsafe {
+                self.device.destroy_buffer(pending_capture.buffer, None);
+            }
+            self.memory_allocator.lock().unwrap().free(pending_capture.allocation)?;
+        }
+
+        Ok(())
+    }
 
-            self.current_frame = (self.current_frame + 1) % Vust::MAX_FRAMES_IN_FLIGHT;
+    /// Copies the requested buffers into host visible ones after everything else in the frame, read back in finish_reads
+    unsafe fn record_reads(&self, command_buffer: vk::CommandBuffer, read_requests: Vec<(vk::Buffer, vk::DeviceSize, ReadCallback)>) -> Result<Vec<PendingRead>, VustError> {
+        let mut pending_reads = Vec::with_capacity(read_requests.len());
+
+        // whatever the frame wrote to the buffers has to land before the copy
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .build()],
+            &[],
+            &[]
+        );
+
+        for (src_buffer, size, callback) in read_requests {
+            let buffer = self.device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .build(),
+                None
+            )?;
+
+            let allocation = self.memory_allocator.lock().unwrap().allocate(&AllocationCreateDesc {
+                name: "buffer readback",
+                requirements: self.device.get_buffer_memory_requirements(buffer),
+                location: MemoryLocation::GpuToCpu,
+                linear: true,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged
+            })?;
+            self.device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())?;
+
+            self.device.cmd_copy_buffer(
+                command_buffer,
+                src_buffer,
+                buffer,
+                &[vk::BufferCopy::builder()
+                    .src_offset(0)
+                    .dst_offset(0)
+                    .size(size)
+                    .build()]
+            );
+
+            pending_reads.push(PendingRead {
+                frame: self.current_frame,
+                buffer,
+                allocation,
+                size,
+                callback
+            });
+        }
+
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)
+                .build()],
+            &[],
+            &[]
+        );
+
+        Ok(pending_reads)
+    }
+
+    /// Records the read into its own command buffer and waits for it instead of the next frame, for Buffer::read()
+    unsafe fn read_buffer_now(&self, buffer: vk::Buffer, size: vk::DeviceSize, callback: ReadCallback) -> Result<(), VustError> {
+        let command_buffer = self.device.allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::builder()
+                .command_pool(self.command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1)
+                .build()
+        )?[0];
+        self.device.begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+                .build()
+        )?;
+
+        let pending_reads = self.record_reads(command_buffer, vec![(buffer, size, callback)])?;
+        self.device.end_command_buffer(command_buffer)?;
+
+        let fence = self.device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+        self.device.queue_submit(self.queue, &[vk::SubmitInfo::builder().command_buffers(&[command_buffer]).build()], fence)?;
+        self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+        self.device.destroy_fence(fence, None);
+        self.device.free_command_buffers(self.command_pool, &[command_buffer]);
+
+        for pending_read in pending_reads {
+            self.finish_read(pending_read)?;
         }
+
+        Ok(())
+    }
+
+    /// Hands the read back bytes of the given frame (all if None) to their callbacks, the frame's fence has to be signaled already
+    fn finish_reads(&mut self, frame: Option<usize>) -> Result<(), VustError> {
+        let pending_reads = std::mem::take(&mut self.pending_reads);
+
+        for pending_read in pending_reads {
+            if frame.is_some_and(|frame| frame != pending_read.frame) {
+                self.pending_reads.push(pending_read);
+                continue;
+            }
+
+            self.finish_read(pending_read)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish_read(&self, pending_read: PendingRead) -> Result<(), VustError> {
+        // GpuToCpu is always mapped
+        let bytes = unsafe { std::slice::from_raw_parts(pending_read.allocation.mapped_ptr().ok_or(VustError::BufferNotMapped)?.as_ptr().cast::<u8>(), pending_read.size as usize) };
+        (pending_read.callback)(bytes);
+
+        unsafe {
+            self.device.destroy_buffer(pending_read.buffer, None);
+        }
+        self.memory_allocator.lock().unwrap().free(pending_read.allocation)?;
+
+        Ok(())
     }
 
     pub fn update_descriptor_set(&self, descriptor: &Descriptor, write_descriptor_infos: &[WriteDescriptorInfo]) {
@@ -799,6 +1412,23 @@ impl InternalVust {
         }
     }
 
+    fn write_descriptor_binding(&self, descriptor: &Descriptor, binding: u32, write_descriptor_info: WriteDescriptorInfo, frame: usize) {
+        let mut write_descriptor_set = descriptor.write_descriptor_set_info[binding as usize][frame];
+        let (buffer_info, image_info) = write_descriptor_info.to_vk();
+
+        if let Some(buffer_info) = &buffer_info {
+            write_descriptor_set.p_buffer_info = buffer_info;
+        }
+        if let Some(image_info) = &image_info {
+            write_descriptor_set.p_image_info = image_info;
+        }
+        write_descriptor_set.descriptor_count = 1;
+
+        unsafe {
+            self.device.update_descriptor_sets(&[write_descriptor_set], &[]);
+        }
+    }
+
     pub fn get_device(&self) -> ash::Device {
         self.device.clone()
     }
@@ -807,25 +1437,627 @@ impl InternalVust {
         self.memory_allocator.clone()
     }
 
+    pub fn get_surface_format(&self) -> vk::SurfaceFormatKHR {
+        self.swapchain_format
+    }
+
+    pub fn get_shared_current_frame(&self) -> Arc<AtomicUsize> {
+        self.shared_current_frame.clone()
+    }
+
+    pub fn get_instance(&self) -> ash::Instance {
+        self.instance.clone()
+    }
+
+    pub fn get_physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+
+    pub fn get_limits(&self) -> vk::PhysicalDeviceLimits {
+        unsafe { self.instance.get_physical_device_properties(self.physical_device).limits }
+    }
+
+    pub fn get_frames_in_flight(&self) -> usize {
+        self.frames_in_flight
+    }
+
+    pub fn get_samples(&self) -> vk::SampleCountFlags {
+        self.samples
+    }
+
     pub fn get_renderpass(&self) -> vk::RenderPass {
         self.renderpass
     }
 
-    pub fn wait_idle(&self) {
+    /// Records the queued texture uploads, the barriers wait for earlier frames still sampling the images
+    fn record_texture_updates(&mut self) {
+        let command_buffer = self.draw_command_buffers[self.current_frame];
+
+        for texture_update in std::mem::take(&mut self.texture_updates) {
+            texture::image_barrier(&self.device, command_buffer, texture_update.image, 0..1, texture_update.layer..texture_update.layer + 1, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+
+            unsafe {
+                self.device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    texture_update.staging_buffer,
+                    texture_update.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[vk::BufferImageCopy::builder()
+                        .buffer_offset(0)
+                        .buffer_row_length(0)
+                        .buffer_image_height(0)
+                        .image_subresource(vk::ImageSubresourceLayers { aspect_mask: vk::ImageAspectFlags::COLOR, mip_level: 0, base_array_layer: texture_update.layer, layer_count: 1 })
+                        .image_offset(texture_update.offset)
+                        .image_extent(texture_update.extent)
+                        .build()]
+                );
+            }
+
+            texture::image_barrier(&self.device, command_buffer, texture_update.image, 0..1, texture_update.layer..texture_update.layer + 1, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        }
+    }
+
+    pub fn wait_idle(&mut self) -> Result<(), VustError> {
         unsafe {
-            self.device.device_wait_idle().unwrap();
+            self.device.device_wait_idle()?;
+        }
+
+        // everything is done, dont leave anyone waiting on a capture or read
+        self.finish_captures(None)?;
+        self.finish_reads(None)
+    }
+
+    /// Only after wait_idle(), textures still holding a cached sampler cant be used after this
+    pub fn destroy_samplers(&mut self) {
+        for (_, sampler) in self.samplers.lock().unwrap().drain(..) {
+            unsafe {
+                self.device.destroy_sampler(sampler, None);
+            }
+        }
+    }
+
+    pub fn get_samplers(&self) -> Arc<Mutex<Vec<(SamplerBuilder, vk::Sampler)>>> {
+        self.samplers.clone()
+    }
+
+    pub fn get_queue_index(&self) -> u32 {
+        self.queue_index
+    }
+
+    /// The surface decides the extent on most platforms, framebuffer_size is only used when it doesnt (wayland)
+    unsafe fn swapchain_extent(surface_util: &extensions::khr::Surface, surface: vk::SurfaceKHR, physical_device: vk::PhysicalDevice, framebuffer_size: (u32, u32)) -> Result<vk::Extent2D, VustError> {
+        let capabilities = surface_util.get_physical_device_surface_capabilities(physical_device, surface)?;
+
+        if capabilities.current_extent.width != u32::MAX {
+            Ok(capabilities.current_extent)
+        } else {
+            Ok(vk::Extent2D {
+                width: framebuffer_size.0.clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
+                height: framebuffer_size.1.clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height)
+            })
         }
     }
 
-    pub fn get_command_pool(&self) -> vk::CommandPool {
-        self.command_pool
+    /// First supported format from the preference list, otherwise any srgb nonlinear format, otherwise the first one the surface reports
+    unsafe fn choose_surface_format(surface_util: &extensions::khr::Surface, surface: vk::SurfaceKHR, physical_device: vk::PhysicalDevice, surface_formats: &[vk::SurfaceFormatKHR]) -> Result<vk::SurfaceFormatKHR, VustError> {
+        let supported_surface_formats = surface_util.get_physical_device_surface_formats(physical_device, surface)?;
+
+        // a single undefined format means the surface doesnt care
+        if let [vk::SurfaceFormatKHR { format: vk::Format::UNDEFINED, .. }] = supported_surface_formats.as_slice() {
+            if let Some(surface_format) = surface_formats.first() {
+                return Ok(*surface_format);
+            }
+        }
+
+        let surface_format = surface_formats
+            .iter()
+            .copied()
+            .find(|surface_format| supported_surface_formats.contains(surface_format))
+            .or_else(|| supported_surface_formats.iter().copied().find(|surface_format| surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR))
+            .or_else(|| supported_surface_formats.first().copied())
+            .ok_or(VustError::NoSupportedFormat("surface"))?;
+
+        #[cfg(debug_assertions)]
+        println!("using surface format: {:?} {:?}", surface_format.format, surface_format.color_space);
+
+        Ok(surface_format)
     }
 
-    pub fn get_queue(&self) -> vk::Queue {
-        self.queue
+    /// First supported mode from the preference list, FIFO is guaranteed to exist so its the fallback
+    unsafe fn choose_present_mode(surface_util: &extensions::khr::Surface, surface: vk::SurfaceKHR, physical_device: vk::PhysicalDevice, present_modes: &[vk::PresentModeKHR]) -> Result<vk::PresentModeKHR, VustError> {
+        let supported_present_modes = surface_util.get_physical_device_surface_present_modes(physical_device, surface)?;
+
+        let present_mode = present_modes
+            .iter()
+            .copied()
+            .find(|present_mode| supported_present_modes.contains(present_mode))
+            .unwrap_or(vk::PresentModeKHR::FIFO);
+
+        #[cfg(debug_assertions)]
+        println!("using present mode: {present_mode:?}");
+
+        Ok(present_mode)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_swapchain(
+        surface_util: &extensions::khr::Surface,
+        surface: vk::SurfaceKHR,
+        swapchain_util: &extensions::khr::Swapchain,
+        physical_device: vk::PhysicalDevice,
+        swapchain_format: vk::SurfaceFormatKHR,
+        present_mode: vk::PresentModeKHR,
+        extent: vk::Extent2D,
+        old_swapchain: vk::SwapchainKHR
+    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>), VustError> {
+        let capabilities = surface_util.get_physical_device_surface_capabilities(physical_device, surface)?;
+
+        let swapchain = swapchain_util.create_swapchain(
+            &vk::SwapchainCreateInfoKHR::builder()
+                .surface(surface)
+                .min_image_count(capabilities.min_image_count + 1)
+                .image_format(swapchain_format.format)
+                .image_color_space(swapchain_format.color_space)
+                .image_extent(extent)
+                .image_array_layers(1)
+                // transfer src for frame captures
+                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC)
+                .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .present_mode(present_mode)
+                .pre_transform(capabilities.current_transform)
+                .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+                .clipped(true)
+                .old_swapchain(old_swapchain)
+                .build(),
+            None
+        )?;
+
+        let images = swapchain_util.get_swapchain_images(swapchain)?;
+
+        Ok((swapchain, images))
+    }
+
+    unsafe fn create_headless_images(instance: &ash::Instance, physical_device: vk::PhysicalDevice, device: &ash::Device, format: vk::Format, extent: vk::Extent2D, count: usize) -> Result<(Vec<vk::Image>, Vec<vk::DeviceMemory>), VustError> {
+        let memory_properties = instance.get_physical_device_memory_properties(physical_device);
+
+        (0..count).map(|_| {
+            let image = device.create_image(
+                &vk::ImageCreateInfo::builder()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .format(format)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .build(),
+                None
+            )?;
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let image_memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::builder()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(Self::find_memory_type(memory_properties, memory_requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL).ok_or(VustError::NoSuitableMemoryType)?)
+                    .build(),
+                None
+            )?;
+
+            device.bind_image_memory(image, image_memory, 0)?;
+
+            Ok((image, image_memory))
+        }).collect::<Result<Vec<_>, VustError>>().map(|images| images.into_iter().unzip())
+    }
+
+    unsafe fn create_swapchain_image_views(device: &ash::Device, images: &[vk::Image], format: vk::Format) -> Result<Vec<vk::ImageView>, VustError> {
+        images.iter().map(|image| {
+            device.create_image_view(
+                &vk::ImageViewCreateInfo::builder()
+                    .image(*image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(format)
+                    .components(vk::ComponentMapping {
+                        r: vk::ComponentSwizzle::IDENTITY,
+                        g: vk::ComponentSwizzle::IDENTITY,
+                        b: vk::ComponentSwizzle::IDENTITY,
+                        a: vk::ComponentSwizzle::IDENTITY
+                    })
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1
+                    })
+                    .build(),
+                None
+            )
+        }).collect::<Result<_, _>>().map_err(VustError::from)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_depth_image(instance: &ash::Instance, physical_device: vk::PhysicalDevice, device: &ash::Device, command_pool: vk::CommandPool, queue: vk::Queue, depth_format: vk::Format, extent: vk::Extent2D, samples: vk::SampleCountFlags) -> Result<(vk::Image, vk::DeviceMemory, vk::ImageView), VustError> {
+        let depth_image = device.create_image(
+            &vk::ImageCreateInfo::builder()
+                .image_type(vk::ImageType::TYPE_2D)
+                .extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .format(depth_format)
+                .samples(samples)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .build(),
+            None
+        )?;
+
+        let depth_image_memory = {
+            let memory_requirements = device.get_image_memory_requirements(depth_image);
+
+            device.allocate_memory(
+                &vk::MemoryAllocateInfo::builder()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(Self::find_memory_type(instance.get_physical_device_memory_properties(physical_device), memory_requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL).ok_or(VustError::NoSuitableMemoryType)?)
+                    .build(),
+                None
+            )?
+        };
+
+        device.bind_image_memory(depth_image, depth_image_memory, 0)?;
+
+        let depth_image_view = device.create_image_view(
+            &vk::ImageViewCreateInfo::builder()
+                .image(depth_image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(depth_format)
+                .components(vk::ComponentMapping {
+                    r: vk::ComponentSwizzle::IDENTITY,
+                    g: vk::ComponentSwizzle::IDENTITY,
+                    b: vk::ComponentSwizzle::IDENTITY,
+                    a: vk::ComponentSwizzle::IDENTITY
+                })
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::DEPTH,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1
+                })
+                .build(),
+            None
+        )?;
+
+        let transition_depth_image_command_buffer = device.allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::builder()
+                .command_pool(command_pool)
+                .command_buffer_count(1)
+                .level(vk::CommandBufferLevel::PRIMARY)
+        )?[0];
+
+        device.begin_command_buffer(
+            transition_depth_image_command_buffer,
+            &vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+                .build(),
+        )?;
+
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(depth_image)
+            .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .subresource_range(
+                vk::ImageSubresourceRange::builder()
+                    .aspect_mask(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL)
+                    .base_mip_level(0)
+                    .level_count(1)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build()
+            )
+        .build();
+
+        device.cmd_pipeline_barrier(
+            transition_depth_image_command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier]
+        );
+
+        device.end_command_buffer(transition_depth_image_command_buffer)?;
+
+        device.queue_submit(
+            queue,
+            &[
+                vk::SubmitInfo::builder()
+                    .command_buffers(&[transition_depth_image_command_buffer])
+                    .build(),
+            ],
+            vk::Fence::null()
+        )?;
+
+        device.queue_wait_idle(queue)?;
+
+        device.free_command_buffers(
+            command_pool,
+            &[transition_depth_image_command_buffer],
+        );
+
+        Ok((depth_image, depth_image_memory, depth_image_view))
+    }
+
+    /// With msaa the multisampled color image is attachment 0 and the swapchain image is the resolve attachment 2
+    /// None without msaa, shared by every swapchain image like the depth image
+    unsafe fn create_msaa_color_image(instance: &ash::Instance, physical_device: vk::PhysicalDevice, device: &ash::Device, color_format: vk::Format, extent: vk::Extent2D, samples: vk::SampleCountFlags) -> Result<Option<(vk::Image, vk::DeviceMemory, vk::ImageView)>, VustError> {
+        if samples == vk::SampleCountFlags::TYPE_1 {
+            return Ok(None);
+        }
+
+        let image = device.create_image(
+            &vk::ImageCreateInfo::builder()
+                .image_type(vk::ImageType::TYPE_2D)
+                .extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .format(color_format)
+                .samples(samples)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .build(),
+            None
+        )?;
+
+        let image_memory = {
+            let memory_requirements = device.get_image_memory_requirements(image);
+
+            device.allocate_memory(
+                &vk::MemoryAllocateInfo::builder()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(Self::find_memory_type(instance.get_physical_device_memory_properties(physical_device), memory_requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL).ok_or(VustError::NoSuitableMemoryType)?)
+                    .build(),
+                None
+            )?
+        };
+
+        device.bind_image_memory(image, image_memory, 0)?;
+
+        let image_view = device.create_image_view(
+            &vk::ImageViewCreateInfo::builder()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(color_format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1
+                })
+                .build(),
+            None
+        )?;
+
+        // the clear renderpass starts from UNDEFINED, no transition needed
+        Ok(Some((image, image_memory, image_view)))
+    }
+
+    unsafe fn create_renderpass(device: &ash::Device, color_format: vk::Format, color_final_layout: vk::ImageLayout, depth_format: vk::Format, samples: vk::SampleCountFlags, load_op: LoadOp) -> Result<vk::RenderPass, VustError> {
+        let msaa = samples != vk::SampleCountFlags::TYPE_1;
+        // the msaa image isnt presented, it stays a color attachment between passes and frames
+        let color_final_layout_msaa = if msaa { vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL } else { color_final_layout };
+
+        let (color_load_op, color_initial_layout, color_dst_access) = match load_op {
+            LoadOp::Clear => (vk::AttachmentLoadOp::CLEAR, vk::ImageLayout::UNDEFINED, vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+            // the image is still in whatever layout the last frame left it in
+            LoadOp::Load => (vk::AttachmentLoadOp::LOAD, color_final_layout_msaa, vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        };
+        // the load renderpass continues the depth and stencil buffer too, begin_main_pass() clears them when a frame starts with it
+        let (depth_load_op, depth_initial_layout) = match load_op {
+            LoadOp::Clear => (vk::AttachmentLoadOp::CLEAR, vk::ImageLayout::UNDEFINED),
+            LoadOp::Load => (vk::AttachmentLoadOp::LOAD, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        };
+
+        let color_attachment = vk::AttachmentDescription::builder()
+            .format(color_format)
+            .samples(samples)
+            .load_op(color_load_op)
+            // kept with msaa too, the load renderpass continues from it after a render target
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(color_initial_layout)
+            .final_layout(color_final_layout_msaa)
+            .build();
+
+        // every pixel is overwritten by the resolve
+        let resolve_attachment = vk::AttachmentDescription::builder()
+            .format(color_format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(color_final_layout)
+            .build();
+
+        let resolve_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(2)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build();
+
+        let color_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build();
+
+        // depth is shared between frames in flight, its stored so a pass resumed after a render target keeps it (and the stencil)
+        let depth_attachment = vk::AttachmentDescription::builder()
+            .format(depth_format)
+            .samples(samples)
+            .load_op(depth_load_op)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(depth_load_op)
+            .stencil_store_op(vk::AttachmentStoreOp::STORE)
+            .initial_layout(depth_initial_layout)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build();
+
+        let depth_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build();
+
+        let attachments = if msaa {
+            vec![color_attachment, depth_attachment, resolve_attachment]
+        } else {
+            vec![color_attachment, depth_attachment]
+        };
+        let color_attachment_refs = [color_attachment_ref];
+        let resolve_attachment_refs = [resolve_attachment_ref];
+
+        let mut subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_refs)
+            .depth_stencil_attachment(&depth_attachment_ref);
+        if msaa {
+            subpass = subpass.resolve_attachments(&resolve_attachment_refs);
+        }
+
+        let renderpass = device.create_render_pass(
+            &vk::RenderPassCreateInfo::builder()
+                .attachments(&attachments)
+                .subpasses(&[subpass.build()])
+                .dependencies(&[
+                    vk::SubpassDependency::builder()
+                        .src_subpass(vk::SUBPASS_EXTERNAL)
+                        .dst_subpass(0)
+                        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+                        .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+                        // the color and depth written before a render target interrupted the main pass (or by an earlier frame with LoadOp::Load)
+                        // have to land before the load renderpass reads them
+                        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                        .dst_access_mask(color_dst_access | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                        .build()
+                ]),
+            None
+        )?;
+
+        Ok(renderpass)
+    }
+
+    unsafe fn create_framebuffers(device: &ash::Device, renderpass: vk::RenderPass, image_views: &[vk::ImageView], depth_image_view: vk::ImageView, msaa_color_view: Option<vk::ImageView>, extent: vk::Extent2D) -> Result<Vec<vk::Framebuffer>, VustError> {
+        image_views.iter().map(|image_view| {
+            let attachments = match msaa_color_view {
+                Some(msaa_color_view) => vec![msaa_color_view, depth_image_view, *image_view],
+                None => vec![*image_view, depth_image_view]
+            };
+            device.create_framebuffer(&vk::FramebufferCreateInfo::builder()
+                .render_pass(renderpass)
+                .attachments(&attachments)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1)
+                .build(), None)
+        }).collect::<Result<_, _>>().map_err(VustError::from)
+    }
+
+    unsafe fn create_surface(entry: &ash::Entry, instance: &ash::Instance, surface_create_info: SurfaceCreateInfo) -> Result<vk::SurfaceKHR, VustError> {
+        match surface_create_info {
+            #[cfg(target_os = "windows")]
+            SurfaceCreateInfo::Win32 { hinstance, hwnd } => {
+                let win32_surface_util = extensions::khr::Win32Surface::new(entry, instance);
+
+                let surface = win32_surface_util.create_win32_surface(
+                    &vk::Win32SurfaceCreateInfoKHR::builder()
+                        .hinstance(hinstance)
+                        .hwnd(hwnd)
+                        .build(),
+                    None
+                )?;
+
+                println!("created win32 vulkan surface");
+                Ok(surface)
+            }
+            #[cfg(target_os = "linux")]
+            SurfaceCreateInfo::Xlib { display, window } => {
+                let xlib_surface_util = extensions::khr::XlibSurface::new(entry, instance);
+
+                let surface = xlib_surface_util.create_xlib_surface(
+                    &vk::XlibSurfaceCreateInfoKHR::builder()
+                        .dpy(display as *mut _)
+                        .window(window)
+                        .build(),
+                    None
+                )?;
+
+                #[cfg(debug_assertions)]
+                println!("created xlib vulkan surface");
+                Ok(surface)
+            }
+            #[cfg(target_os = "linux")]
+            SurfaceCreateInfo::Xcb { connection, window } => {
+                let xcb_surface_util = extensions::khr::XcbSurface::new(entry, instance);
+
+                let surface = xcb_surface_util.create_xcb_surface(
+                    &vk::XcbSurfaceCreateInfoKHR::builder()
+                        .connection(connection as *mut _)
+                        .window(window)
+                        .build(),
+                    None
+                )?;
+
+                #[cfg(debug_assertions)]
+                println!("created xcb vulkan surface");
+                Ok(surface)
+            }
+            #[cfg(target_os = "linux")]
+            SurfaceCreateInfo::Wayland { display, surface } => {
+                let wayland_surface_util = extensions::khr::WaylandSurface::new(entry, instance);
+
+                let surface = wayland_surface_util.create_wayland_surface(
+                    &vk::WaylandSurfaceCreateInfoKHR::builder()
+                        .display(display as *mut _)
+                        .surface(surface as *mut _)
+                        .build(),
+                    None
+                )?;
+
+                #[cfg(debug_assertions)]
+                println!("created wayland vulkan surface");
+                Ok(surface)
+            }
+            // no surface create info given, SurfaceCreateInfo::Headless should be used to render without a window
+            _ => Err(VustError::UnsupportedSurface)
+        }
     }
 
     /// yoinked from ash examples
+    #[cfg(debug_assertions)]
     unsafe extern "system" fn vulkan_debug_callback(
         message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
         message_type: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -860,13 +2092,7 @@ impl InternalVust {
         type_filter: u32,
         properties: vk::MemoryPropertyFlags
     ) -> Option<u32> {
-        for i in 0..memory_properties.memory_type_count {
-            // dont really know how this works ¯\_(ツ)_/¯
-            if (type_filter & (1 << i)) > 0 && ((memory_properties.memory_types[i as usize].property_flags & properties) == properties) {
-                return Some(i as u32);
-            }
-        }
-
-        None
+        // dont really know how this works ¯\_(ツ)_/¯
+        (0..memory_properties.memory_type_count).find(|&i| (type_filter & (1 << i)) > 0 && ((memory_properties.memory_types[i as usize].property_flags & properties) == properties))
     }
 }
@@ -1,6 +1,14 @@
+pub mod capture;
 pub mod create_info;
+pub mod error;
 pub mod buffer;
+pub mod dynamic_buffer;
+pub mod uniform_ring;
 pub mod texture;
+mod texture_file;
+pub mod sampler;
+pub mod render_target;
+pub mod render_graph;
 pub mod pipeline;
 pub mod write_descriptor_info;
 pub mod descriptor;
@@ -13,36 +21,52 @@ pub use ash::vk::{make_api_version, VertexInputBindingDescription, VertexInputAt
 pub use ash::Device;
 pub use gpu_allocator::vulkan::Allocator;
 pub use vk::{Viewport, Rect2D, Offset2D, Extent2D};
-use create_info::VustCreateInfo;
+pub use bytemuck::{Pod, Zeroable};
+use buffer::TypedBuffer;
+use capture::CapturedImage;
+use create_info::{LoadOp, VustCreateInfo};
 use descriptor::Descriptor;
-use gpu_allocator::vulkan::{Allocation, AllocatorCreateDesc};
+use error::VustError;
+use gpu_allocator::vulkan::Allocation;
 use internal_vust::InternalVust;
-use pipeline::GraphicsPipeline;
-use vust_command::VustCommand;
+use render_graph::CompiledRenderGraph;
+use render_target::RenderTarget;
+use sampler::SamplerBuilder;
+use vust_command::{ReadCallback, VustCommand};
 use write_descriptor_info::WriteDescriptorInfo;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
-use std::{ffi::{CStr, CString}, sync::{Arc, Mutex}};
-use ash::{extensions, vk};
+use std::{ffi::CStr, sync::{Arc, Mutex}};
+use ash::vk;
 
 use crate::vust_sync::VustSyncer;
 
 /// This struct acts more like a handle and can be cloned and used anywhere
 #[derive(Clone)]
 pub struct Vust {
+    instance: ash::Instance,
+    physical_device: vk::PhysicalDevice,
     device: ash::Device,
     memory_allocator: Arc<Mutex<Allocator>>,
     renderpass: vk::RenderPass,
-    command_pool: vk::CommandPool,
-    queue: vk::Queue,
+    samples: vk::SampleCountFlags,
+    queue_index: u32,
+    /// transient pool of every single exec command buffer that hasnt been submitted yet, the render thread's pool cant be touched from other threads
+    single_exec_pools: Arc<Mutex<Vec<(vk::CommandBuffer, vk::CommandPool)>>>,
+    surface_format: vk::SurfaceFormatKHR,
+    frames_in_flight: usize,
+    current_frame: Arc<AtomicUsize>,
+    limits: vk::PhysicalDeviceLimits,
+    features: vk::PhysicalDeviceFeatures,
+    /// every sampler built so far, shared between textures with the same configuration
+    samplers: Arc<Mutex<Vec<(SamplerBuilder, vk::Sampler)>>>,
+    render_thread: std::thread::ThreadId,
     vust_sender: mpsc::Sender<VustCommand>
 }
 
 impl Vust {
     pub const NAME: &'static str = "Vust";
-    pub const C_NAME: &'static CStr = unsafe {
-        CStr::from_bytes_with_nul_unchecked(b"Vust\0")
-    };
+    pub const C_NAME: &'static CStr = c"Vust";
 
     pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
     /// used for vulkan
@@ -57,254 +81,456 @@ impl Vust {
         vk::make_api_version(0, major, minor, patch)
     };
 
-    pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
+    /// used unless VustCreateInfo::with_frames_in_flight() says otherwise
+    pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
 
-    pub fn new(create_info: VustCreateInfo) -> (Self, VustSyncer) {
-        let mut vust = InternalVust::new(create_info);
+    pub fn new(create_info: VustCreateInfo) -> Result<(Self, VustSyncer), VustError> {
+        let mut vust = InternalVust::new(create_info)?;
         let device = vust.get_device();
         let memory_allocator = vust.get_memory_allocator();
         let renderpass = vust.get_renderpass();
-        let command_pool = vust.get_command_pool();
-        let queue = vust.get_queue();
+        let samples = vust.get_samples();
+        let queue_index = vust.get_queue_index();
+        let samplers = vust.get_samplers();
+        let surface_format = vust.get_surface_format();
+        let frames_in_flight = vust.get_frames_in_flight();
+        let current_frame = vust.get_shared_current_frame();
+        let limits = vust.get_limits();
+        let instance = vust.get_instance();
+        let physical_device = vust.get_physical_device();
+        let features = unsafe { instance.get_physical_device_features(physical_device) };
         
         let (vust_sender, vust_receiver) = mpsc::channel();
-        let (vust_sync_sender, vust_sync_receiver) = mpsc::channel::<()>();
+        let (vust_sync_sender, vust_sync_receiver) = mpsc::channel::<Result<bool, VustError>>();
 
-        std::thread::spawn(move || {
+        let render_thread = std::thread::spawn(move || {
             // take ownership
             let vust_sync_sender = vust_sync_sender;
             while let Ok(command) = vust_receiver.recv() {
-                match command {
+                let result = match command {
                     VustCommand::KYS => {
-                        vust.wait_idle();
+                        // nothing to report the error to after this, samplers could still be in use if the gpu didnt go idle
+                        if vust.wait_idle().is_ok() {
+                            vust.destroy_samplers();
+                        }
                         break;
                     }
                     command => vust.run(command, &vust_sync_sender)
+                };
+
+                // the next sync() gets the error, every command after that gets RenderThreadDead
+                if let Err(err) = result {
+                    #[cfg(debug_assertions)]
+                    println!("render thread died: {err}");
+
+                    let _ = vust_sync_sender.send(Err(err));
+                    break;
                 }
             }
-        });
+        }).thread().id();
 
-        (
+        Ok((
             Self {
+                instance,
+                physical_device,
                 device,
                 memory_allocator,
                 renderpass,
-                command_pool,
-                queue,
+                samples,
+                queue_index,
+                single_exec_pools: Arc::new(Mutex::new(Vec::new())),
+                surface_format,
+                frames_in_flight,
+                current_frame,
+                limits,
+                features,
+                samplers,
+                render_thread,
                 vust_sender
             },
             VustSyncer {
                 allow_messages_recv: vust_sync_receiver
             }
-        )
+        ))
     }
 
-    pub fn destroy_buffer(&self, buffer: vk::Buffer, allocation: Allocation) {
-        self.vust_sender.send(VustCommand::DestroyBuffer { buffer, allocation }).unwrap();
+    /// Format and color space the swapchain (or headless images) were created with
+    pub fn surface_format(&self) -> vk::SurfaceFormatKHR {
+        self.surface_format
     }
 
-    pub fn destroy_texture(&self, image: vk::Image, view: vk::ImageView, sampler: vk::Sampler, allocation: Allocation) {
-        self.vust_sender.send(VustCommand::DestroyTexture { image, view, sampler, allocation }).unwrap();
+    /// Samples per pixel of the main pass, what VustCreateInfo::with_msaa() asked for clamped to the gpu's limits
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        self.samples
     }
 
-    pub fn reset_command_buffer(&self) {
-        self.vust_sender.send(VustCommand::ResetCommandBuffer).unwrap();
+    /// How many frames can be recorded while the gpu is still working on the previous ones
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight
     }
 
-    pub fn bind_pipeline(&self, pipeline_handle: vk::Pipeline) {
-        self.vust_sender.send(VustCommand::BindPipeline { pipeline_handle }).unwrap();
+    /// What the gpu can do with the format, checked before creating textures with it
+    pub fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        unsafe { self.instance.get_physical_device_format_properties(self.physical_device, format) }
     }
 
-    pub fn bind_viewport(&self, viewport: vk::Viewport) {
-        self.vust_sender.send(VustCommand::BindViewport { viewport }).unwrap();
+    /// Whether textures can be created and sampled in the format, use it to pick between BCn/ETC2/ASTC versions of an asset
+    pub fn supports_texture_format(&self, format: vk::Format) -> bool {
+        let features = self.format_properties(format).optimal_tiling_features;
+        features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::TRANSFER_DST)
     }
 
-    pub fn bind_scissor(&self, scissor: vk::Rect2D) {
-        self.vust_sender.send(VustCommand::BindScissor { scissor }).unwrap();
+    /// Frame in flight being recorded, valid between VustSyncer.sync() and the next Vust.reset_command_buffer()
+    pub fn current_frame(&self) -> usize {
+        self.current_frame.load(Ordering::Acquire)
     }
 
-    pub fn bind_descriptor_set(&self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor) {
-        self.vust_sender.send(VustCommand::BindDescriptorSet { pipeline_layout, descriptor: descriptor.clone() }).unwrap();
+    pub fn destroy_buffer(&self, buffer: vk::Buffer, allocation: Allocation) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::DestroyBuffer { buffer, allocation })?;
+        Ok(())
     }
 
-    pub fn bind_vertex_buffer(&self, vertex_buffer: vk::Buffer) {
-        self.vust_sender.send(VustCommand::BindVertexBuffer { vertex_buffer }).unwrap();
+    /// Samplers are cached and shared, so they arent destroyed with the texture
+    pub fn destroy_texture(&self, image: vk::Image, view: vk::ImageView, allocation: Allocation) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::DestroyTexture { image, view, allocation })?;
+        Ok(())
     }
 
-    pub fn bind_index_buffer(&self, index_buffer: vk::Buffer) {
-        self.vust_sender.send(VustCommand::BindIndexBuffer { index_buffer }).unwrap();
+    /// Renderpass and framebuffer of a dropped RenderTarget, its images go through destroy_texture()
+    pub fn destroy_render_target(&self, renderpass: vk::RenderPass, framebuffer: vk::Framebuffer) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::DestroyRenderTarget { renderpass, framebuffer })?;
+        Ok(())
     }
 
-    pub fn draw(&self, vertex_count: u32) {
-        self.vust_sender.send(VustCommand::Draw { vertex_count }).unwrap();
+    /// Recreates the swapchain with the new framebuffer size before the next frame
+    /// 
+    /// Out of date/suboptimal swapchains are handled automatically, this is mostly needed on wayland where the surface doesnt know its own size
+    pub fn resize(&self, width: u32, height: u32) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::Resize { width, height })?;
+        Ok(())
     }
 
-    pub fn draw_indexed(&self, index_count: u32) {
-        self.vust_sender.send(VustCommand::DrawIndexed { index_count }).unwrap();
+    /// true uses FIFO, false prefers MAILBOX then IMMEDIATE (falls back to FIFO if neither is supported)
+    /// 
+    /// The swapchain is recreated on the render thread before the next frame
+    pub fn set_vsync(&self, vsync: bool) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::SetVsync { vsync })?;
+        Ok(())
     }
 
-    pub fn update_descriptor_set(&self, descriptor: &Descriptor, write_descriptor_infos: Vec<WriteDescriptorInfo>) {
-        self.vust_sender.send(VustCommand::UpdateDescriptorSet { descriptor: descriptor.clone(), write_descriptor_infos: write_descriptor_infos.clone() }).unwrap();
+    /// Color the next frames are cleared to, stays until changed
+    pub fn set_clear_color(&self, color: [f32; 4]) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::SetClearColor { color })?;
+        Ok(())
     }
 
-    pub fn update_descriptor_set_once(&self, descriptor: &Descriptor, write_descriptor_infos: Vec<WriteDescriptorInfo>) {
-        // 2 unsafe blocks to write both descriptors, theres a prettier/smarter way to do this but im lazy
+    /// Depth and stencil values the next frames are cleared to, defaults to 1.0 and 0
+    pub fn set_clear_depth_stencil(&self, depth: f32, stencil: u32) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::SetClearDepthStencil { depth, stencil })?;
+        Ok(())
+    }
 
-        unsafe {
-            let mut write_descriptor_info = descriptor.write_descriptor_set_info
-                .iter()
-                .map(|write_descriptor_infos| write_descriptor_infos[0])
-                .collect::<Vec<_>>();
-
-            // im holding the infos in a vec for the duration of this function's scope to avoid ptr lifetime issues when i pass the pointer to buffer/image info into a vk::WriteDescriptorSet
-            enum BufferOrImageInfo {
-                Buffer(vk::DescriptorBufferInfo),
-                Image(vk::DescriptorImageInfo)
-            }
+    /// Clear or keep the color contents at the start of the next frames, see LoadOp
+    pub fn set_load_op(&self, load_op: LoadOp) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::SetLoadOp { load_op })?;
+        Ok(())
+    }
 
-            let infos = write_descriptor_infos
-                .iter()
-                .map(|write_descriptor_info| {
-                    match write_descriptor_info {
-                        WriteDescriptorInfo::Buffer { buffer, offset, range } => {
-                            BufferOrImageInfo::Buffer(vk::DescriptorBufferInfo::builder()
-                                .buffer(*buffer)
-                                .offset(*offset)
-                                .range(*range)
-                                .build())
-                        }
-                        WriteDescriptorInfo::Image { image_view, sampler } => {
-                            BufferOrImageInfo::Image(vk::DescriptorImageInfo::builder()
-                                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                                .image_view(*image_view)
-                                .sampler(*sampler)
-                                .build())
-                        }
-                    }
-                })
-                .collect::<Vec<BufferOrImageInfo>>();
+    /// Copies the next rendered frame (the one between the next reset_command_buffer and render_surface) into RGBA8 pixels
+    /// 
+    /// The image arrives on the receiver once the gpu is done with that frame, the receiver disconnects if the format cant be converted or the render thread dies
+    pub fn capture_frame(&self) -> Result<mpsc::Receiver<CapturedImage>, VustError> {
+        let (sender, receiver) = mpsc::channel();
+        self.vust_sender.send(VustCommand::CaptureFrame { sender })?;
+        Ok(receiver)
+    }
 
-            for (i, write_descriptor_info) in write_descriptor_info.iter_mut().enumerate() {
-                match &infos[i] {
-                    BufferOrImageInfo::Buffer(buffer) => {
-                        write_descriptor_info.p_buffer_info = buffer;
-                    }
-                    BufferOrImageInfo::Image(image) => {
-                        write_descriptor_info.p_image_info = image;
-                    }
-                }
-                write_descriptor_info.descriptor_count = 1;
-            }
+    /// Copies the buffer into a host visible one at the end of the current frame, the callback gets the bytes once the frame is done
+    /// 
+    /// Used by Buffer::read_async()
+    pub fn read_buffer(&self, buffer: vk::Buffer, size: vk::DeviceSize, callback: ReadCallback) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::ReadBuffer { buffer, size, callback })?;
+        Ok(())
+    }
 
-            self.device.update_descriptor_sets(
-                &write_descriptor_info,
-                &[]
-            );
-        }
+    /// Same as read_buffer() but the render thread submits the copy on its own right away instead of waiting for the frame
+    /// 
+    /// Used by Buffer::read()
+    pub fn read_buffer_now(&self, buffer: vk::Buffer, size: vk::DeviceSize, callback: ReadCallback) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::ReadBufferNow { buffer, size, callback })?;
+        Ok(())
+    }
 
-        unsafe {
-            let mut write_descriptor_info = descriptor.write_descriptor_set_info
-                .iter()
-                .map(|write_descriptor_infos| write_descriptor_infos[1])
-                .collect::<Vec<_>>();
-
-            // im holding the infos in a vec for the duration of this function's scope to avoid ptr lifetime issues when i pass the pointer to buffer/image info into a vk::WriteDescriptorSet
-            enum BufferOrImageInfo {
-                Buffer(vk::DescriptorBufferInfo),
-                Image(vk::DescriptorImageInfo)
-            }
+    /// Copies the staging buffer into the first mip level of the image's layer at the start of the next frame, the image has to be in SHADER_READ_ONLY_OPTIMAL
+    /// 
+    /// Used by Texture::update_layer_region(), the staging buffer has to stay alive until that frame is done
+    pub fn update_texture(&self, staging_buffer: vk::Buffer, image: vk::Image, layer: u32, offset: vk::Offset3D, extent: vk::Extent3D) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::UpdateTexture { staging_buffer, image, layer, offset, extent })?;
+        Ok(())
+    }
 
-            let infos = write_descriptor_infos
-                .iter()
-                .map(|write_descriptor_info| {
-                    match write_descriptor_info {
-                        WriteDescriptorInfo::Buffer { buffer, offset, range } => {
-                            BufferOrImageInfo::Buffer(vk::DescriptorBufferInfo::builder()
-                                .buffer(*buffer)
-                                .offset(*offset)
-                                .range(*range)
-                                .build())
-                        }
-                        WriteDescriptorInfo::Image { image_view, sampler } => {
-                            BufferOrImageInfo::Image(vk::DescriptorImageInfo::builder()
-                                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                                .image_view(*image_view)
-                                .sampler(*sampler)
-                                .build())
+    pub fn reset_command_buffer(&self) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::ResetCommandBuffer)?;
+        Ok(())
+    }
+
+    pub fn bind_pipeline(&self, pipeline_handle: vk::Pipeline) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindPipeline { pipeline_handle })?;
+        Ok(())
+    }
+
+    pub fn bind_viewport(&self, viewport: vk::Viewport) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindViewport { viewport })?;
+        Ok(())
+    }
+
+    pub fn bind_scissor(&self, scissor: vk::Rect2D) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindScissor { scissor })?;
+        Ok(())
+    }
+
+    /// Stencil reference for pipelines made with DepthStencilState.dynamic_stencil_reference, set it after binding one
+    pub fn set_stencil_reference(&self, face_mask: vk::StencilFaceFlags, reference: u32) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::SetStencilReference { face_mask, reference })?;
+        Ok(())
+    }
+
+    pub fn bind_descriptor_set(&self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindDescriptorSet { pipeline_layout, descriptor: descriptor.clone(), dynamic_offsets: Vec::new() })?;
+        Ok(())
+    }
+
+    /// For descriptors with UNIFORM_BUFFER_DYNAMIC/STORAGE_BUFFER_DYNAMIC bindings, one offset per dynamic binding in binding order
+    /// 
+    /// The offsets usually come from UniformRing::allocate()
+    pub fn bind_descriptor_set_with_offsets(&self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor, dynamic_offsets: &[u32]) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindDescriptorSet { pipeline_layout, descriptor: descriptor.clone(), dynamic_offsets: dynamic_offsets.to_vec() })?;
+        Ok(())
+    }
+
+    pub fn bind_vertex_buffer(&self, vertex_buffer: vk::Buffer) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindVertexBuffer { vertex_buffer })?;
+        Ok(())
+    }
+
+    pub fn bind_index_buffer(&self, index_buffer: vk::Buffer) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BindIndexBuffer { index_buffer })?;
+        Ok(())
+    }
+
+    pub fn draw(&self, vertex_count: u32) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::Draw { vertex_count })?;
+        Ok(())
+    }
+
+    pub fn draw_indexed(&self, index_count: u32) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::DrawIndexed { index_count })?;
+        Ok(())
+    }
+
+    /// Draws after this go into the target until end_render_target(), the target is cleared first
+    /// 
+    /// Can be used any number of times per frame. If something was already drawn to the surface this frame, it continues afterwards without clearing the color, depth or stencil
+    pub fn begin_render_target(&self, render_target: &RenderTarget) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::BeginRenderTarget {
+            renderpass: render_target.renderpass(),
+            framebuffer: render_target.framebuffer(),
+            extent: render_target.extent(),
+            clear_values: render_target.clear_values()
+        })?;
+        Ok(())
+    }
+
+    /// Draws go back to the surface, the target's color image can be sampled by them
+    pub fn end_render_target(&self) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::EndRenderTarget)?;
+        Ok(())
+    }
+
+    /// Records the graph's passes into this frame, surface passes are drawn before render_surface() like normal draws
+    /// 
+    /// Whatever was drawn to the surface before this keeps its color, depth and stencil, surface passes continue on top of it
+    pub fn execute_render_graph(&self, render_graph: &CompiledRenderGraph) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::ExecuteRenderGraph { render_graph: render_graph.clone() })?;
+        Ok(())
+    }
+
+    /// Binds the buffer and draws every vertex in it, T should be one whole vertex
+    pub fn draw_buffer<T: Pod>(&self, vertex_buffer: &TypedBuffer<T>) -> Result<(), VustError> {
+        self.bind_vertex_buffer(vertex_buffer.handle())?;
+        self.draw(vertex_buffer.len() as u32)
+    }
+
+    /// Binds both buffers and draws every index in the index buffer
+    pub fn draw_indexed_buffer<T: Pod>(&self, vertex_buffer: &TypedBuffer<T>, index_buffer: &TypedBuffer<u32>) -> Result<(), VustError> {
+        self.bind_vertex_buffer(vertex_buffer.handle())?;
+        self.bind_index_buffer(index_buffer.handle())?;
+        self.draw_indexed(index_buffer.len() as u32)
+    }
+
+    pub fn update_descriptor_set(&self, descriptor: &Descriptor, write_descriptor_infos: Vec<WriteDescriptorInfo>) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::UpdateDescriptorSet { descriptor: descriptor.clone(), write_descriptor_infos: write_descriptor_infos.clone() })?;
+        Ok(())
+    }
+
+    /// Rewrites a single binding of the descriptor for every frame in flight, the frame being recorded right away and the others once the gpu is done with them
+    pub fn update_descriptor_binding(&self, descriptor: &Descriptor, binding: u32, write_descriptor_info: WriteDescriptorInfo) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::UpdateDescriptorBinding { descriptor: descriptor.clone(), binding, write_descriptor_info })?;
+        Ok(())
+    }
+
+    pub fn update_descriptor_set_once(&self, descriptor: &Descriptor, write_descriptor_infos: Vec<WriteDescriptorInfo>) {
+        // writes the same infos to every frame's descriptor set
+        for frame in 0..self.frames_in_flight {
+            unsafe {
+                let mut write_descriptor_info = descriptor.write_descriptor_set_info
+                    .iter()
+                    .map(|write_descriptor_infos| write_descriptor_infos[frame])
+                    .collect::<Vec<_>>();
+
+                // im holding the infos in a vec for the duration of this function's scope to avoid ptr lifetime issues when i pass the pointer to buffer/image info into a vk::WriteDescriptorSet
+                enum BufferOrImageInfo {
+                    Buffer(vk::DescriptorBufferInfo),
+                    Image(vk::DescriptorImageInfo)
+                }
+
+                let infos = write_descriptor_infos
+                    .iter()
+                    .map(|write_descriptor_info| {
+                        match write_descriptor_info {
+                            WriteDescriptorInfo::Buffer { buffer, offset, range } => {
+                                BufferOrImageInfo::Buffer(vk::DescriptorBufferInfo::builder()
+                                    .buffer(*buffer)
+                                    .offset(*offset)
+                                    .range(*range)
+                                    .build())
+                            }
+                            WriteDescriptorInfo::Image { image_view, sampler } => {
+                                BufferOrImageInfo::Image(vk::DescriptorImageInfo::builder()
+                                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                                    .image_view(*image_view)
+                                    .sampler(*sampler)
+                                    .build())
+                            }
                         }
-                    }
-                })
-                .collect::<Vec<BufferOrImageInfo>>();
+                    })
+                    .collect::<Vec<BufferOrImageInfo>>();
 
-            for (i, write_descriptor_info) in write_descriptor_info.iter_mut().enumerate() {
-                match &infos[i] {
-                    BufferOrImageInfo::Buffer(buffer) => {
-                        write_descriptor_info.p_buffer_info = buffer;
-                    }
-                    BufferOrImageInfo::Image(image) => {
-                        write_descriptor_info.p_image_info = image;
+                for (i, write_descriptor_info) in write_descriptor_info.iter_mut().enumerate() {
+                    match &infos[i] {
+                        BufferOrImageInfo::Buffer(buffer) => {
+                            write_descriptor_info.p_buffer_info = buffer;
+                        }
+                        BufferOrImageInfo::Image(image) => {
+                            write_descriptor_info.p_image_info = image;
+                        }
                     }
+                    write_descriptor_info.descriptor_count = 1;
                 }
-                write_descriptor_info.descriptor_count = 1;
-            }
 
-            self.device.update_descriptor_sets(
-                &write_descriptor_info,
-                &[]
-            );
+                self.device.update_descriptor_sets(
+                    &write_descriptor_info,
+                    &[]
+                );
+            }
         }
     }
 
-    pub fn render_surface(&mut self) {
-        self.vust_sender.send(VustCommand::RenderSurface).unwrap();
+    pub fn render_surface(&mut self) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::RenderSurface)?;
+        Ok(())
     }
 
-    pub fn wait_idle(&self) {
-        self.vust_sender.send(VustCommand::KYS).unwrap();
+    /// Shuts the render thread down once the gpu is idle and destroys the cached samplers, nothing can be rendered after this
+    pub fn wait_idle(&self) -> Result<(), VustError> {
+        self.vust_sender.send(VustCommand::KYS)?;
+        Ok(())
     }
 
-    pub fn begin_single_exec_command(&self) -> vk::CommandBuffer {
+    /// Records into a command buffer from its own pool, so any thread can use it
+    /// 
+    /// Except the render thread (render graph record functions), ending it waits for the render thread to submit it
+    pub fn begin_single_exec_command(&self) -> Result<vk::CommandBuffer, VustError> {
+        self.check_not_render_thread()?;
+
         unsafe {
-            let command_buffer = self.device.allocate_command_buffers(
+            let command_pool = self.device.create_command_pool(
+                &vk::CommandPoolCreateInfo::builder()
+                    .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                    .queue_family_index(self.queue_index)
+                    .build(),
+                None
+            )?;
+
+            let command_buffer = match self.device.allocate_command_buffers(
                 &vk::CommandBufferAllocateInfo::builder()
-                    .command_pool(self.command_pool)
+                    .command_pool(command_pool)
                     .level(vk::CommandBufferLevel::PRIMARY)
                     .command_buffer_count(1)
                     .build(),
-            ).unwrap()[0];
+            ) {
+                Ok(command_buffers) => command_buffers[0],
+                Err(err) => {
+                    self.device.destroy_command_pool(command_pool, None);
+                    return Err(err.into());
+                }
+            };
+            self.single_exec_pools.lock().unwrap().push((command_buffer, command_pool));
     
             self.device.begin_command_buffer(
                 command_buffer,
                 &vk::CommandBufferBeginInfo::builder()
                     .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
                     .build(),
-            ).unwrap();
+            )?;
     
-            command_buffer
+            Ok(command_buffer)
         }
     }
     
-    pub fn end_single_exec_command(&self, command_buffer: vk::CommandBuffer) {
+    /// The render thread submits it since it owns the queue, blocks until the gpu is done with it
+    pub fn end_single_exec_command(&self, command_buffer: vk::CommandBuffer) -> Result<(), VustError> {
+        let command_pool = {
+            let mut single_exec_pools = self.single_exec_pools.lock().unwrap();
+            let index = single_exec_pools.iter().position(|(pool_command_buffer, _)| *pool_command_buffer == command_buffer).expect("command buffer wasnt made by begin_single_exec_command");
+            single_exec_pools.swap_remove(index).1
+        };
+
+        let result = unsafe { self.submit_single_exec(command_buffer) };
+
         unsafe {
-            self.device.end_command_buffer(command_buffer).unwrap();
-            let command_buffers = [command_buffer];
-            self.device.queue_submit(
-                self.queue,
-                &[
-                    vk::SubmitInfo::builder()
-                        .command_buffers(&command_buffers)
-                        .build(),
-                ],
-                vk::Fence::null()
-            ).unwrap();
-    
-            self.device.queue_wait_idle(self.queue).unwrap();
-    
-            self.device.free_command_buffers(
-                self.command_pool,
-                &command_buffers,
-            );
+            // destroying the pool frees the command buffer too
+            self.device.destroy_command_pool(command_pool, None);
         }
+
+        result
+    }
+
+    /// Anything that blocks until the render thread answers would deadlock when called from it
+    pub(crate) fn check_not_render_thread(&self) -> Result<(), VustError> {
+        if std::thread::current().id() == self.render_thread {
+            return Err(VustError::OnRenderThread);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn submit_single_exec(&self, command_buffer: vk::CommandBuffer) -> Result<(), VustError> {
+        self.device.end_command_buffer(command_buffer)?;
+        let fence = self.device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let result = match self.vust_sender.send(VustCommand::SubmitSingleExec { command_buffer, fence, sender }) {
+            Ok(()) => match receiver.recv() {
+                Ok(Ok(())) => self.device.wait_for_fences(&[fence], true, u64::MAX).map_err(VustError::from),
+                Ok(Err(err)) => Err(err),
+                Err(_) => Err(VustError::RenderThreadDead)
+            }
+            Err(err) => Err(err.into())
+        };
+
+        self.device.destroy_fence(fence, None);
+        result
     }
 
     pub fn transition_image_layout(
@@ -312,8 +538,8 @@ impl Vust {
         image: vk::Image,
         old_layout: vk::ImageLayout,
         new_layout: vk::ImageLayout
-    ) {
-        let transition_command_buffer = self.begin_single_exec_command();
+    ) -> Result<(), VustError> {
+        let transition_command_buffer = self.begin_single_exec_command()?;
     
         let mut barrier = vk::ImageMemoryBarrier::builder()
             .old_layout(old_layout)
@@ -347,7 +573,7 @@ impl Vust {
     
             (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
         } else {
-            unreachable!()
+            return Err(VustError::UnsupportedLayoutTransition { old_layout, new_layout });
         };
         let barriers = [barrier];
         unsafe {
@@ -362,6 +588,6 @@ impl Vust {
             );
         }
     
-        self.end_single_exec_command(transition_command_buffer);
+        self.end_single_exec_command(transition_command_buffer)
     }
 }
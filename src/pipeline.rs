@@ -1,33 +1,47 @@
-pub use vk::{PrimitiveTopology, PolygonMode, DescriptorType, ShaderStageFlags};
+pub use vk::{PrimitiveTopology, PolygonMode, DescriptorType, ShaderStageFlags, CompareOp, StencilOp, StencilOpState, StencilFaceFlags};
 
 use std::ffi::CString;
 use ash::vk::{self, VertexInputAttributeDescription, VertexInputBindingDescription};
-use crate::{descriptor::Descriptor, Vust};
+use crate::{descriptor::Descriptor, error::VustError, render_target::RenderTarget, Vust};
 
 pub struct GraphicsPipeline {
     descriptor_pool_create_info: Option<(vk::DescriptorPoolCreateInfo, Vec<vk::DescriptorPoolSize>)>,
     descriptor_set_layout: vk::DescriptorSetLayout,
-    write_descriptor_set_info: Vec<[vk::WriteDescriptorSet; Vust::MAX_FRAMES_IN_FLIGHT]>,
+    write_descriptor_set_info: Vec<Vec<vk::WriteDescriptorSet>>,
     pipeline_layout: vk::PipelineLayout,
     pipeline: vk::Pipeline
 }
 
 impl GraphicsPipeline {
-    pub fn new(vust: &Vust, create_info: GraphicsPipelineCreateInfo) -> Self {
+    pub fn new(vust: &Vust, create_info: GraphicsPipelineCreateInfo) -> Result<Self, VustError> {
+        Self::create(vust, create_info, vust.renderpass, vust.samples)
+    }
+
+    /// Pipeline for drawing into the render target (or any with the same formats) instead of the surface
+    pub fn new_for_render_target(vust: &Vust, render_target: &RenderTarget, create_info: GraphicsPipelineCreateInfo) -> Result<Self, VustError> {
+        Self::create(vust, create_info, render_target.renderpass(), vk::SampleCountFlags::TYPE_1)
+    }
+
+    /// Returns VustError::MissingFeature if depth bounds are used without the gpu supporting them
+    fn create(vust: &Vust, create_info: GraphicsPipelineCreateInfo, renderpass: vk::RenderPass, samples: vk::SampleCountFlags) -> Result<Self, VustError> {
+        if create_info.depth_stencil.depth_bounds.is_some() && vust.features.depth_bounds == vk::FALSE {
+            return Err(VustError::MissingFeature("depthBounds"));
+        }
+
         unsafe {
             let vertex_input_state = vust.device.create_shader_module(&vk::ShaderModuleCreateInfo {
                 s_type: vk::StructureType::SHADER_MODULE_CREATE_INFO,
                 code_size: create_info.vertex_bin.len(),
                 p_code: create_info.vertex_bin.as_ptr() as *const u32,
                 ..Default::default()
-            }, None).unwrap();
+            }, None)?;
 
             let fragment_input_state = vust.device.create_shader_module(&vk::ShaderModuleCreateInfo {
                 s_type: vk::StructureType::SHADER_MODULE_CREATE_INFO,
                 code_size: create_info.fragment_bin.len(),
                 p_code: create_info.fragment_bin.as_ptr() as *const u32,
                 ..Default::default()
-            }, None).unwrap();
+            }, None)?;
 
             let entry_point_name = CString::new("main").unwrap();
 
@@ -55,8 +69,8 @@ impl GraphicsPipeline {
                 .build();
 
             // doing this cuz release build deletes &[viewport] out of existence
-            let mut hold_viewport = None;
-            let mut hold_scissor = None;
+            let hold_viewport;
+            let hold_scissor;
             // absolute unit of a match statement
             let viewport_state_info = match (create_info.viewport.clone(), create_info.scissor.clone()) {
                 (Viewport::Dynamic, Scissor::Dynamic) => {
@@ -132,9 +146,13 @@ impl GraphicsPipeline {
                 .depth_bias_enable(false)
                 .build();
 
+            // sample shading needs the device feature, without it the pipeline just shades per pixel
+            let min_sample_shading = create_info.multisample.min_sample_shading.filter(|_| vust.features.sample_rate_shading == vk::TRUE);
             let multisample_info = vk::PipelineMultisampleStateCreateInfo::builder()
-                .rasterization_samples(vk::SampleCountFlags::TYPE_1)
-                .sample_shading_enable(false)
+                .rasterization_samples(samples)
+                .sample_shading_enable(min_sample_shading.is_some())
+                .min_sample_shading(min_sample_shading.unwrap_or(0.0).clamp(0.0, 1.0))
+                .alpha_to_coverage_enable(create_info.multisample.alpha_to_coverage)
                 .build();
 
             let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
@@ -170,9 +188,9 @@ impl GraphicsPipeline {
                 Vec::new()
             };
 
-            let descriptor_set_layout = vust.device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings).build(), None).unwrap();
+            let descriptor_set_layout = vust.device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings).build(), None)?;
             let descriptor_set_layouts = [descriptor_set_layout];
-            let pipeline_layout = vust.device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::builder().set_layouts(&descriptor_set_layouts).build(), None).unwrap();
+            let pipeline_layout = vust.device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::builder().set_layouts(&descriptor_set_layouts).build(), None)?;
 
             let mut dynamic_states = Vec::new();
 
@@ -182,15 +200,26 @@ impl GraphicsPipeline {
             if let Scissor::Dynamic = create_info.scissor {
                 dynamic_states.push(vk::DynamicState::SCISSOR);
             }
+            if create_info.depth_stencil.dynamic_stencil_reference {
+                dynamic_states.push(vk::DynamicState::STENCIL_REFERENCE);
+            }
 
             let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states).build();
 
+            let depth_stencil = create_info.depth_stencil;
+            let (min_depth_bounds, max_depth_bounds) = depth_stencil.depth_bounds.unwrap_or((0.0, 1.0));
+            let (front, back) = depth_stencil.stencil.unwrap_or_default();
+
             let depth_stencil_info = vk::PipelineDepthStencilStateCreateInfo::builder()
-                .depth_test_enable(create_info.enable_depth_test)
-                .depth_write_enable(true)
-                .depth_compare_op(vk::CompareOp::LESS)
-                .depth_bounds_test_enable(false)
-                .stencil_test_enable(false)
+                .depth_test_enable(depth_stencil.depth_test)
+                .depth_write_enable(depth_stencil.depth_write)
+                .depth_compare_op(depth_stencil.depth_compare_op)
+                .depth_bounds_test_enable(depth_stencil.depth_bounds.is_some())
+                .min_depth_bounds(min_depth_bounds)
+                .max_depth_bounds(max_depth_bounds)
+                .stencil_test_enable(depth_stencil.stencil.is_some())
+                .front(front)
+                .back(back)
                 .build();
 
             let pipeline = vust.device.create_graphics_pipelines(
@@ -207,12 +236,12 @@ impl GraphicsPipeline {
                         .color_blend_state(&color_blend_info)
                         .dynamic_state(&dynamic_state_info)
                         .layout(pipeline_layout)
-                        .render_pass(vust.renderpass)
+                        .render_pass(renderpass)
                         .subpass(0)
                         .build()
                 ],
                 None
-            ).unwrap()[0];
+            ).map_err(|(_, result)| result)?[0];
 
             let descriptor_pool_create_info = if let Some(descriptor_set_layout) = &create_info.descriptor_set_layout {
                 let pool_sizes = descriptor_set_layout.bindings
@@ -220,14 +249,14 @@ impl GraphicsPipeline {
                     .map(|bindings| {
                         vk::DescriptorPoolSize::builder()
                             .ty(bindings.descriptor_type)
-                            .descriptor_count(Vust::MAX_FRAMES_IN_FLIGHT as u32)
+                            .descriptor_count(vust.frames_in_flight as u32)
                             .build()
                     })
                     .collect::<Vec<_>>();
 
                 Some((
                     vk::DescriptorPoolCreateInfo::builder()
-                        .max_sets(Vust::MAX_FRAMES_IN_FLIGHT as u32)
+                        .max_sets(vust.frames_in_flight as u32)
                         .pool_sizes(&pool_sizes)
                         .build(),
                     pool_sizes
@@ -238,52 +267,52 @@ impl GraphicsPipeline {
 
             let write_descriptor_set_info = if let Some(descriptor_set_layout) = create_info.descriptor_set_layout {
                 descriptor_set_layout.bindings.iter().enumerate().map(|(i, descriptor_set_binding)| {
-                    let writes = [   
+                    vec![
                         vk::WriteDescriptorSet::builder()
                             .dst_binding(i as u32)
                             .dst_array_element(0)
                             .descriptor_type(descriptor_set_binding.descriptor_type)
-                            .build(); Vust::MAX_FRAMES_IN_FLIGHT
-                    ];
-
-                    writes
+                            .build();
+                        vust.frames_in_flight
+                    ]
                 }).collect::<Vec<_>>()
             } else {
                 vec![]
             };
 
-            GraphicsPipeline {
+            Ok(GraphicsPipeline {
                 descriptor_pool_create_info,
                 descriptor_set_layout,
                 write_descriptor_set_info,
                 pipeline_layout,
                 pipeline
-            }
+            })
         }
     }
 
-    pub fn create_descriptor(&self, vust: &Vust) -> Option<Descriptor> {
+    /// Returns VustError::NoDescriptorSetLayout if the pipeline was created without one
+    pub fn create_descriptor(&self, vust: &Vust) -> Result<Descriptor, VustError> {
         unsafe {
             let descriptor_pool = vust.device.create_descriptor_pool(
-                &self.descriptor_pool_create_info.as_ref()?.0,
+                &self.descriptor_pool_create_info.as_ref().ok_or(VustError::NoDescriptorSetLayout)?.0,
                 None
-            ).ok()?;
+            )?;
 
-            let descriptor_set: [vk::DescriptorSet; Vust::MAX_FRAMES_IN_FLIGHT] = vust.device.allocate_descriptor_sets(
+            let descriptor_set = vust.device.allocate_descriptor_sets(
                 &vk::DescriptorSetAllocateInfo::builder()
                     .descriptor_pool(descriptor_pool)
-                    .set_layouts(&[self.descriptor_set_layout; Vust::MAX_FRAMES_IN_FLIGHT])
-            ).unwrap().try_into().unwrap();
+                    .set_layouts(&vec![self.descriptor_set_layout; vust.frames_in_flight])
+            )?;
             
             let write_descriptor_set_info = self.write_descriptor_set_info.clone().into_iter().map(|mut write_descriptor_infos| {
-                for i in 0..Vust::MAX_FRAMES_IN_FLIGHT {
-                    write_descriptor_infos[i].dst_set = descriptor_set[i];
+                for (write_descriptor_info, descriptor_set) in write_descriptor_infos.iter_mut().zip(&descriptor_set) {
+                    write_descriptor_info.dst_set = *descriptor_set;
                 }
 
                 write_descriptor_infos
             }).collect::<Vec<_>>();
 
-            Some(Descriptor {
+            Ok(Descriptor {
                 descriptor_pool,
                 descriptor_set,
                 write_descriptor_set_info
@@ -312,7 +341,52 @@ pub struct GraphicsPipelineCreateInfo {
     pub polygon_mode: vk::PolygonMode,
     pub cull_mode: CullMode,
     pub descriptor_set_layout: Option<DescriptorSetLayout>,
-    pub enable_depth_test: bool
+    pub depth_stencil: DepthStencilState,
+    pub multisample: MultisampleState
+}
+
+/// Default has the depth test and stencil off, depth writes on with LESS
+///
+/// ``` ignore
+/// // transparent pass, tested against the scene but doesnt write depth
+/// DepthStencilState { depth_test: true, depth_write: false, ..Default::default() }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DepthStencilState {
+    pub depth_test: bool,
+    /// only does something with depth_test on
+    pub depth_write: bool,
+    pub depth_compare_op: vk::CompareOp,
+    /// min and max (0.0 - 1.0), fragments are dropped if the depth already in the buffer is outside of it. Needs the depthBounds feature
+    pub depth_bounds: Option<(f32, f32)>,
+    /// front and back face stencil ops, None turns the stencil test off
+    ///
+    /// the main pass' depth buffer has 8 stencil bits, render targets only have them with a stencil depth format
+    pub stencil: Option<(vk::StencilOpState, vk::StencilOpState)>,
+    /// the reference in stencil is ignored, set it with Vust::set_stencil_reference() before drawing instead
+    pub dynamic_stencil_reference: bool
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self {
+            depth_test: false,
+            depth_write: true,
+            depth_compare_op: vk::CompareOp::LESS,
+            depth_bounds: None,
+            stencil: None,
+            dynamic_stencil_reference: false
+        }
+    }
+}
+
+/// Only does something in pipelines drawing into the main pass with VustCreateInfo::with_msaa()
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MultisampleState {
+    /// fragment alpha decides how many samples are covered, for foliage/fences without sorting
+    pub alpha_to_coverage: bool,
+    /// shades at least this fraction (0.0 - 1.0) of the samples separately instead of once per pixel, ignored if the gpu cant
+    pub min_sample_shading: Option<f32>
 }
 
 #[derive(Debug, Clone)]
@@ -368,6 +442,7 @@ pub struct DescriptorSetLayout {
 }
 
 pub struct DescriptorSetBinding {
+    /// UNIFORM_BUFFER_DYNAMIC bindings take their offset when binding, see Vust::bind_descriptor_set_with_offsets() and UniformRing
     pub descriptor_type: vk::DescriptorType,
     pub stage_flags: vk::ShaderStageFlags
 }
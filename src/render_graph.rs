@@ -0,0 +1,478 @@
+use std::sync::{Arc, Mutex};
+use ash::vk;
+use crate::{buffer::TypedBuffer, descriptor::Descriptor, error::VustError, internal_vust::InternalVust, render_target::RenderTarget, Pod, Vust};
+
+/// Something a pass reads or writes, used to order the passes and put barriers between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphResource {
+    /// read as vertex/index/uniform/storage data, written as storage data from shaders
+    Buffer(vk::Buffer),
+    /// color image of a RenderTarget, written by passes drawing into it and sampled by the rest
+    RenderTarget(vk::Image),
+    /// attachment that only lives inside the graph, from RenderGraph::add_transient()
+    Transient(usize)
+}
+
+impl From<&RenderTarget> for GraphResource {
+    fn from(render_target: &RenderTarget) -> Self {
+        GraphResource::RenderTarget(render_target.image())
+    }
+}
+
+impl<T: Pod> From<&TypedBuffer<T>> for GraphResource {
+    fn from(buffer: &TypedBuffer<T>) -> Self {
+        GraphResource::Buffer(buffer.handle())
+    }
+}
+
+/// Description of a transient attachment, transients with the same description whose passes dont overlap share one RenderTarget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransientTarget {
+    pub dimensions: (u32, u32),
+    pub color_format: vk::Format,
+    pub depth_format: Option<vk::Format>
+}
+
+/// What a pass draws into
+enum PassTarget {
+    Surface,
+    RenderTarget(TargetInfo),
+    Transient(usize)
+}
+
+#[derive(Clone)]
+pub(crate) struct TargetInfo {
+    pub(crate) renderpass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) clear_values: Vec<vk::ClearValue>,
+    image: vk::Image
+}
+
+impl TargetInfo {
+    fn new(render_target: &RenderTarget) -> Self {
+        Self {
+            renderpass: render_target.renderpass(),
+            framebuffer: render_target.framebuffer(),
+            extent: render_target.extent(),
+            clear_values: render_target.clear_values(),
+            image: render_target.image()
+        }
+    }
+}
+
+type RecordFn = Box<dyn FnMut(&mut PassContext) + Send>;
+
+/// One pass of a RenderGraph, draws into the surface unless a target is given
+pub struct GraphPass {
+    name: String,
+    target: PassTarget,
+    reads: Vec<GraphResource>,
+    writes: Vec<GraphResource>,
+    record: Option<RecordFn>
+}
+
+impl GraphPass {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            target: PassTarget::Surface,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            record: None
+        }
+    }
+
+    /// Surface passes always run after every other pass
+    pub fn with_surface_target(mut self) -> Self {
+        self.target = PassTarget::Surface;
+        self
+    }
+
+    pub fn with_render_target(mut self, render_target: &RenderTarget) -> Self {
+        self.target = PassTarget::RenderTarget(TargetInfo::new(render_target));
+        self
+    }
+
+    /// resource has to come from RenderGraph::add_transient(), panics otherwise
+    pub fn with_transient_target(mut self, resource: GraphResource) -> Self {
+        let GraphResource::Transient(index) = resource else {
+            panic!("pass \"{}\" was given {resource:?} as its transient target, use RenderGraph::add_transient() or with_render_target()", self.name);
+        };
+        self.target = PassTarget::Transient(index);
+        self
+    }
+
+    pub fn with_read(mut self, resource: impl Into<GraphResource>) -> Self {
+        self.reads.push(resource.into());
+        self
+    }
+
+    /// The pass' own target doesnt need to be declared
+    pub fn with_write(mut self, resource: impl Into<GraphResource>) -> Self {
+        self.writes.push(resource.into());
+        self
+    }
+
+    /// Runs on the render thread every time the graph is executed, inside the pass' renderpass
+    ///
+    /// Nothing in it can wait for the render thread: staged uploads (writing device local buffers, growing a DynamicBuffer, creating textures) and Buffer.read() of device local buffers return VustError::OnRenderThread
+    ///
+    /// Passes drawing into transients need pipelines made for the transient's render target, give them theirs with CompiledRenderGraph.set_record() after compiling
+    pub fn with_record(mut self, record: impl FnMut(&mut PassContext) + Send + 'static) -> Self {
+        self.record = Some(Box::new(record));
+        self
+    }
+
+    /// Declared writes plus the target
+    fn all_writes(&self) -> Vec<GraphResource> {
+        let mut writes = self.writes.clone();
+        match &self.target {
+            PassTarget::Surface => {},
+            PassTarget::RenderTarget(target_info) => writes.push(GraphResource::RenderTarget(target_info.image)),
+            PassTarget::Transient(index) => writes.push(GraphResource::Transient(*index))
+        }
+        writes
+    }
+}
+
+/// Passes that declare what they read and write, vust orders them, puts the barriers between them and shares transient attachments
+///
+/// ``` ignore
+/// let mut render_graph = RenderGraph::new();
+/// let shadow_map = render_graph.add_transient(TransientTarget { dimensions: (1024, 1024), color_format: vk::Format::R32_SFLOAT, depth_format: Some(vk::Format::D32_SFLOAT) });
+/// render_graph.add_pass(GraphPass::new("shadows").with_transient_target(shadow_map).with_record(move |pass| { .. }));
+/// render_graph.add_pass(GraphPass::new("main").with_read(shadow_map).with_record(move |pass| { .. }));
+/// let render_graph = render_graph.compile(&vust)?;
+///
+/// // every frame
+/// vust.reset_command_buffer()?;
+/// vust_syncer.sync()?;
+/// vust.execute_render_graph(&render_graph)?;
+/// vust.render_surface()?;
+/// ```
+#[derive(Default)]
+pub struct RenderGraph {
+    passes: Vec<GraphPass>,
+    transients: Vec<TransientTarget>
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_transient(&mut self, transient_target: TransientTarget) -> GraphResource {
+        self.transients.push(transient_target);
+        GraphResource::Transient(self.transients.len() - 1)
+    }
+
+    /// Order doesnt matter unless two passes write the same resource, then they run in the order they were added
+    pub fn add_pass(&mut self, pass: GraphPass) {
+        self.passes.push(pass);
+    }
+
+    /// Sorts the passes, creates the transient render targets and works out the barriers
+    ///
+    /// Returns VustError::RenderGraphCycle if passes depend on each other and VustError::UnknownGraphResource for transients from another graph
+    pub fn compile(self, vust: &Vust) -> Result<CompiledRenderGraph, VustError> {
+        for pass in &self.passes {
+            let transient_out_of_bounds = |resource: &GraphResource| matches!(resource, GraphResource::Transient(index) if *index >= self.transients.len());
+            if pass.reads.iter().chain(&pass.all_writes()).any(transient_out_of_bounds) {
+                return Err(VustError::UnknownGraphResource(pass.name.clone()));
+            }
+        }
+
+        let order = self.sort()?;
+        let transient_targets = self.alias_transients(vust, &order)?;
+
+        let mut passes = Vec::with_capacity(order.len());
+        // last pass in the order to write/read each resource so far
+        let mut last_writes = Vec::<GraphResource>::new();
+        let mut last_reads = Vec::<GraphResource>::new();
+
+        for &index in &order {
+            let pass = &self.passes[index];
+            let writes = pass.all_writes();
+
+            let mut barriers = Vec::<BufferBarrier>::new();
+            for resource in pass.reads.iter().chain(&writes) {
+                let GraphResource::Buffer(buffer) = resource else {
+                    // render target and transient images are synchronized by their renderpass' dependencies
+                    continue;
+                };
+                if barriers.iter().any(|barrier| barrier.buffer == *buffer) {
+                    continue;
+                }
+                let written_before = last_writes.contains(resource);
+                let read_before = last_reads.contains(resource);
+                let writing = writes.contains(resource);
+
+                if written_before || (writing && read_before) {
+                    barriers.push(BufferBarrier { buffer: *buffer, after_write: written_before, before_write: writing });
+                }
+            }
+
+            for resource in &writes {
+                if !last_writes.contains(resource) {
+                    last_writes.push(*resource);
+                }
+            }
+            for resource in &pass.reads {
+                if !last_reads.contains(resource) {
+                    last_reads.push(*resource);
+                }
+            }
+
+            passes.push((index, barriers));
+        }
+
+        let mut graph_passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+        let passes = passes.into_iter().map(|(index, barriers)| {
+            let pass = graph_passes[index].take().unwrap();
+            let target = match pass.target {
+                PassTarget::Surface => None,
+                PassTarget::RenderTarget(target_info) => Some(target_info),
+                PassTarget::Transient(transient) => Some(TargetInfo::new(&transient_targets.0[transient_targets.1[transient]]))
+            };
+
+            CompiledPass {
+                name: pass.name,
+                target,
+                barriers,
+                record: Mutex::new(pass.record)
+            }
+        }).collect::<Vec<_>>();
+
+        #[cfg(debug_assertions)]
+        println!("compiled render graph: {:?}, {} transients in {} render targets", passes.iter().map(|pass| &pass.name).collect::<Vec<_>>(), transient_targets.1.len(), transient_targets.0.len());
+
+        Ok(CompiledRenderGraph {
+            passes: Arc::new(passes),
+            render_targets: Arc::new(transient_targets.0),
+            transient_targets: Arc::new(transient_targets.1)
+        })
+    }
+
+    /// Writers before readers, surface passes last, ties keep the order the passes were added in
+    fn sort(&self) -> Result<Vec<usize>, VustError> {
+        let pass_count = self.passes.len();
+        let mut dependencies = vec![Vec::new(); pass_count];
+
+        for (reader, reader_pass) in self.passes.iter().enumerate() {
+            let reader_writes = reader_pass.all_writes();
+            for (writer, writer_pass) in self.passes.iter().enumerate() {
+                if reader == writer {
+                    continue;
+                }
+
+                let writer_writes = writer_pass.all_writes();
+                // passes that read and write the same resource (accumulating into it) go in the order they were added, like two writers
+                let reads_its_output = reader_pass.reads.iter().any(|resource| writer_writes.contains(resource) && (writer < reader || !reader_writes.contains(resource)));
+                let writes_after_it = writer < reader && reader_writes.iter().any(|resource| writer_writes.contains(resource));
+                let surface_last = matches!(reader_pass.target, PassTarget::Surface) && !matches!(writer_pass.target, PassTarget::Surface);
+
+                if reads_its_output || writes_after_it || surface_last {
+                    dependencies[reader].push(writer);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(pass_count);
+        let mut done = vec![false; pass_count];
+        while order.len() < pass_count {
+            let next = (0..pass_count).find(|&pass| !done[pass] && dependencies[pass].iter().all(|&dependency| done[dependency]));
+            match next {
+                Some(pass) => {
+                    done[pass] = true;
+                    order.push(pass);
+                }
+                None => {
+                    let pass = (0..pass_count).find(|&pass| !done[pass]).unwrap();
+                    return Err(VustError::RenderGraphCycle(self.passes[pass].name.clone()));
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Gives every transient a render target, reusing ones whose last pass ran before the transient's first
+    fn alias_transients(&self, vust: &Vust, order: &[usize]) -> Result<(Vec<RenderTarget>, Vec<usize>), VustError> {
+        let lifetime = |transient: usize| {
+            let uses = order.iter().enumerate().filter(|(_, &index)| {
+                let pass = &self.passes[index];
+                pass.reads.iter().chain(&pass.all_writes()).any(|resource| *resource == GraphResource::Transient(transient))
+            }).map(|(position, _)| position).collect::<Vec<_>>();
+
+            (uses.first().copied().unwrap_or(0), uses.last().copied().unwrap_or(0))
+        };
+
+        let mut transients = (0..self.transients.len()).map(|transient| (transient, lifetime(transient))).collect::<Vec<_>>();
+        transients.sort_by_key(|(_, (first, _))| *first);
+
+        // description and last use of every render target made so far
+        let mut render_targets = Vec::<(RenderTarget, TransientTarget, usize)>::new();
+        let mut transient_targets = vec![0; self.transients.len()];
+
+        for (transient, (first, last)) in transients {
+            let description = self.transients[transient];
+            let free = render_targets.iter().position(|(_, target_description, target_last)| *target_description == description && *target_last < first);
+
+            transient_targets[transient] = match free {
+                Some(render_target) => {
+                    render_targets[render_target].2 = last;
+                    render_target
+                }
+                None => {
+                    let mut render_target_builder = RenderTarget::builder()
+                        .with_name("Transient Render Target")
+                        .with_dimensions(description.dimensions)
+                        .with_color_format(description.color_format);
+                    if let Some(depth_format) = description.depth_format {
+                        render_target_builder = render_target_builder.with_depth_format(depth_format);
+                    }
+
+                    render_targets.push((render_target_builder.build(vust)?, description, last));
+                    render_targets.len() - 1
+                }
+            };
+        }
+
+        Ok((render_targets.into_iter().map(|(render_target, _, _)| render_target).collect(), transient_targets))
+    }
+}
+
+/// Barrier before a pass for a buffer an earlier pass used
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BufferBarrier {
+    pub(crate) buffer: vk::Buffer,
+    /// an earlier pass wrote it, make the writes visible
+    pub(crate) after_write: bool,
+    /// this pass writes it, wait for earlier reads
+    pub(crate) before_write: bool
+}
+
+impl BufferBarrier {
+    const SHADER_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_raw(vk::PipelineStageFlags::VERTEX_SHADER.as_raw() | vk::PipelineStageFlags::FRAGMENT_SHADER.as_raw());
+
+    /// src stage, dst stage, src access, dst access
+    pub(crate) fn masks(&self) -> (vk::PipelineStageFlags, vk::PipelineStageFlags, vk::AccessFlags, vk::AccessFlags) {
+        let read_stages = vk::PipelineStageFlags::VERTEX_INPUT | Self::SHADER_STAGES;
+        let read_access = vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ | vk::AccessFlags::UNIFORM_READ | vk::AccessFlags::SHADER_READ;
+
+        let (src_stage, src_access) = if self.after_write {
+            (Self::SHADER_STAGES, vk::AccessFlags::SHADER_WRITE)
+        } else {
+            (read_stages, vk::AccessFlags::empty())
+        };
+        let (dst_stage, dst_access) = if self.before_write {
+            (read_stages, read_access | vk::AccessFlags::SHADER_WRITE)
+        } else {
+            (read_stages, read_access)
+        };
+
+        (src_stage, dst_stage, src_access, dst_access)
+    }
+}
+
+pub(crate) struct CompiledPass {
+    pub(crate) name: String,
+    /// None draws into the surface
+    pub(crate) target: Option<TargetInfo>,
+    pub(crate) barriers: Vec<BufferBarrier>,
+    pub(crate) record: Mutex<Option<RecordFn>>
+}
+
+/// Sorted graph ready for Vust.execute_render_graph(), cloning it is cheap
+///
+/// Owns the transient render targets, keep it alive until the frames using it are done
+#[derive(Clone)]
+pub struct CompiledRenderGraph {
+    pub(crate) passes: Arc<Vec<CompiledPass>>,
+    render_targets: Arc<Vec<RenderTarget>>,
+    /// render target index for every transient
+    transient_targets: Arc<Vec<usize>>
+}
+
+impl CompiledRenderGraph {
+    /// Render target behind a transient, for pipelines (GraphicsPipeline::new_for_render_target()) and descriptors of passes that sample it
+    pub fn render_target(&self, resource: GraphResource) -> Option<&RenderTarget> {
+        match resource {
+            GraphResource::Transient(index) => self.transient_targets.get(index).map(|render_target| &self.render_targets[*render_target]),
+            _ => None
+        }
+    }
+
+    /// Replaces the record function of the pass with this name, returns false if there is none
+    ///
+    /// Takes effect from the next execute_render_graph()
+    pub fn set_record(&self, pass_name: &str, record: impl FnMut(&mut PassContext) + Send + 'static) -> bool {
+        match self.passes.iter().find(|pass| pass.name == pass_name) {
+            Some(pass) => {
+                *pass.record.lock().unwrap() = Some(Box::new(record));
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Pass names in the order they run
+    pub fn pass_order(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name.as_str()).collect()
+    }
+
+    /// How many render targets the transients ended up sharing
+    pub fn transient_render_target_count(&self) -> usize {
+        self.render_targets.len()
+    }
+}
+
+/// Handed to a pass' record function on the render thread, records straight into the frame's command buffer
+///
+/// Upload data before executing the graph, staged uploads from a record function return VustError::OnRenderThread
+pub struct PassContext<'a> {
+    pub(crate) internal_vust: &'a mut InternalVust
+}
+
+impl PassContext<'_> {
+    pub fn bind_pipeline(&mut self, pipeline_handle: vk::Pipeline) {
+        self.internal_vust.bind_pipeline(pipeline_handle);
+    }
+
+    pub fn bind_viewport(&mut self, viewport: vk::Viewport) {
+        self.internal_vust.bind_viewport(viewport);
+    }
+
+    pub fn bind_scissor(&mut self, scissor: vk::Rect2D) {
+        self.internal_vust.bind_scissor(scissor);
+    }
+
+    pub fn set_stencil_reference(&mut self, face_mask: vk::StencilFaceFlags, reference: u32) {
+        self.internal_vust.set_stencil_reference(face_mask, reference);
+    }
+
+    pub fn bind_descriptor_set(&mut self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor) {
+        self.internal_vust.bind_descriptor_set(pipeline_layout, descriptor, &[]);
+    }
+
+    pub fn bind_descriptor_set_with_offsets(&mut self, pipeline_layout: vk::PipelineLayout, descriptor: &Descriptor, dynamic_offsets: &[u32]) {
+        self.internal_vust.bind_descriptor_set(pipeline_layout, descriptor, dynamic_offsets);
+    }
+
+    pub fn bind_vertex_buffer(&mut self, vertex_buffer: vk::Buffer) {
+        self.internal_vust.bind_vertex_buffer(vertex_buffer);
+    }
+
+    pub fn bind_index_buffer(&mut self, index_buffer: vk::Buffer) {
+        self.internal_vust.bind_index_buffer(index_buffer);
+    }
+
+    pub fn draw(&mut self, vertex_count: u32) {
+        self.internal_vust.draw(vertex_count);
+    }
+
+    pub fn draw_indexed(&mut self, index_count: u32) {
+        self.internal_vust.draw_indexed(index_count);
+    }
+}
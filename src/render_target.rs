@@ -0,0 +1,316 @@
+use ash::vk;
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc};
+use crate::{error::VustError, sampler::{Sampler, SamplerBuilder}, texture, write_descriptor_info::WriteDescriptorInfo, Vust};
+
+/// Offscreen color (and optionally depth) image to draw into between Vust.begin_render_target() and Vust.end_render_target()
+///
+/// The color image is sampled afterwards like a texture, bind it with write_descriptor_info(). Pipelines drawing into it are made with GraphicsPipeline::new_for_render_target()
+pub struct RenderTarget {
+    extent: vk::Extent2D,
+    color_format: vk::Format,
+    depth_format: Option<vk::Format>,
+    color: Option<(vk::Image, vk::ImageView, Allocation)>,
+    depth: Option<(vk::Image, vk::ImageView, Allocation)>,
+    renderpass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    sampler: Sampler,
+    clear_color: [f32; 4],
+    vust: Vust
+}
+
+impl RenderTarget {
+    pub fn builder() -> RenderTargetBuilder {
+        RenderTargetBuilder {
+            name: "Render Target".to_string(),
+            dimensions: (0, 0),
+            color_format: vk::Format::R8G8B8A8_UNORM,
+            depth_format: None,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            sampler: None
+        }
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn color_format(&self) -> vk::Format {
+        self.color_format
+    }
+
+    pub fn depth_format(&self) -> Option<vk::Format> {
+        self.depth_format
+    }
+
+    /// Color image
+    pub fn image(&self) -> vk::Image {
+        self.color.as_ref().unwrap().0
+    }
+
+    /// Color image view
+    pub fn view(&self) -> vk::ImageView {
+        self.color.as_ref().unwrap().1
+    }
+
+    pub fn sampler(&self) -> vk::Sampler {
+        self.sampler.handle()
+    }
+
+    pub fn renderpass(&self) -> vk::RenderPass {
+        self.renderpass
+    }
+
+    pub fn framebuffer(&self) -> vk::Framebuffer {
+        self.framebuffer
+    }
+
+    pub fn clear_values(&self) -> Vec<vk::ClearValue> {
+        let mut clear_values = vec![vk::ClearValue { color: vk::ClearColorValue { float32: self.clear_color } }];
+        if self.depth.is_some() {
+            clear_values.push(vk::ClearValue { depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 } });
+        }
+        clear_values
+    }
+
+    /// Color image with the target's sampler, for COMBINED_IMAGE_SAMPLER bindings
+    pub fn write_descriptor_info(&self) -> WriteDescriptorInfo {
+        WriteDescriptorInfo::Image {
+            image_view: self.view(),
+            sampler: self.sampler.handle()
+        }
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        // if the render thread is already dead theres nothing left to free it
+        let _ = self.vust.destroy_render_target(self.renderpass, self.framebuffer);
+        for (image, view, allocation) in [self.color.take(), self.depth.take()].into_iter().flatten() {
+            let _ = self.vust.destroy_texture(image, view, allocation);
+        }
+    }
+}
+
+pub struct RenderTargetBuilder {
+    name: String,
+    dimensions: (u32, u32),
+    color_format: vk::Format,
+    depth_format: Option<vk::Format>,
+    clear_color: [f32; 4],
+    sampler: Option<SamplerBuilder>
+}
+
+impl RenderTargetBuilder {
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_dimensions(mut self, dimensions: (u32, u32)) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Defaults to R8G8B8A8_UNORM
+    pub fn with_color_format(mut self, color_format: vk::Format) -> Self {
+        self.color_format = color_format;
+        self
+    }
+
+    /// Without this the target has no depth buffer, it gets cleared to 1.0 every time the target is begun
+    pub fn with_depth_format(mut self, depth_format: vk::Format) -> Self {
+        self.depth_format = Some(depth_format);
+        self
+    }
+
+    /// Color the target is cleared to every time its begun
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Without this the target gets a LINEAR CLAMP_TO_EDGE sampler
+    pub fn with_sampler(mut self, sampler: SamplerBuilder) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Returns VustError::UnsupportedTextureFormat if the color format cant be rendered to and sampled, or the depth format cant be a depth attachment
+    pub fn build(self, vust: &Vust) -> Result<RenderTarget, VustError> {
+        let color_features = vust.format_properties(self.color_format).optimal_tiling_features;
+        if !color_features.contains(vk::FormatFeatureFlags::COLOR_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+            return Err(VustError::UnsupportedTextureFormat(self.color_format));
+        }
+        if let Some(depth_format) = self.depth_format {
+            if !vust.format_properties(depth_format).optimal_tiling_features.contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT) {
+                return Err(VustError::UnsupportedTextureFormat(depth_format));
+            }
+        }
+
+        let extent = vk::Extent2D { width: self.dimensions.0.max(1), height: self.dimensions.1.max(1) };
+
+        unsafe {
+            let color = create_attachment(vust, &self.name, self.color_format, extent, vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED, vk::ImageAspectFlags::COLOR)?;
+            let depth = match self.depth_format {
+                Some(depth_format) => Some(create_attachment(vust, &self.name, depth_format, extent, vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT, vk::ImageAspectFlags::DEPTH)?),
+                None => None
+            };
+
+            // sampling before the first draw should see a valid (if undefined) image
+            let command_buffer = vust.begin_single_exec_command()?;
+            texture::image_barrier(&vust.device, command_buffer, color.0, 0..1, 0..1, vk::ImageLayout::UNDEFINED, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+            vust.end_single_exec_command(command_buffer)?;
+
+            let renderpass = create_renderpass(vust, self.color_format, self.depth_format)?;
+
+            let attachments = [Some(color.1), depth.as_ref().map(|depth| depth.1)].into_iter().flatten().collect::<Vec<_>>();
+            let framebuffer = vust.device.create_framebuffer(
+                &vk::FramebufferCreateInfo::builder()
+                    .render_pass(renderpass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1)
+                    .build(),
+                None
+            )?;
+
+            let sampler = self.sampler.unwrap_or(
+                Sampler::builder()
+                    .with_filter(vk::Filter::LINEAR)
+                    .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            ).build(vust)?;
+
+            Ok(RenderTarget {
+                extent,
+                color_format: self.color_format,
+                depth_format: self.depth_format,
+                color: Some(color),
+                depth,
+                renderpass,
+                framebuffer,
+                sampler,
+                clear_color: self.clear_color,
+                vust: vust.clone()
+            })
+        }
+    }
+}
+
+unsafe fn create_attachment(vust: &Vust, name: &str, format: vk::Format, extent: vk::Extent2D, usage: vk::ImageUsageFlags, aspect_mask: vk::ImageAspectFlags) -> Result<(vk::Image, vk::ImageView, Allocation), VustError> {
+    let image = vust.device.create_image(
+        &vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .build(),
+        None
+    )?;
+
+    let allocation = vust.memory_allocator.lock().unwrap().allocate(
+        &AllocationCreateDesc {
+            name,
+            requirements: vust.device.get_image_memory_requirements(image),
+            location: gpu_allocator::MemoryLocation::GpuOnly,
+            linear: false,
+            allocation_scheme: gpu_allocator::vulkan::AllocationScheme::GpuAllocatorManaged
+        }
+    )?;
+    vust.device.bind_image_memory(image, allocation.memory(), allocation.offset())?;
+
+    let view = vust.device.create_image_view(
+        &vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1
+            })
+            .build(),
+        None
+    )?;
+
+    Ok((image, view, allocation))
+}
+
+/// Color ends up ready to be sampled, the dependencies wait for earlier frames sampling it before clearing and make the writes visible to shaders after
+unsafe fn create_renderpass(vust: &Vust, color_format: vk::Format, depth_format: Option<vk::Format>) -> Result<vk::RenderPass, VustError> {
+    let mut attachments = vec![
+        vk::AttachmentDescription::builder()
+            .format(color_format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build()
+    ];
+    if let Some(depth_format) = depth_format {
+        attachments.push(
+            vk::AttachmentDescription::builder()
+                .format(depth_format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build()
+        );
+    }
+
+    let color_attachment_refs = [vk::AttachmentReference { attachment: 0, layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL }];
+    let depth_attachment_ref = vk::AttachmentReference { attachment: 1, layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL };
+
+    let mut subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_attachment_refs);
+    if depth_format.is_some() {
+        subpass = subpass.depth_stencil_attachment(&depth_attachment_ref);
+    }
+
+    let shader_stages = vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER;
+    let attachment_stages = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+
+    let renderpass = vust.device.create_render_pass(
+        &vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&[subpass.build()])
+            .dependencies(&[
+                vk::SubpassDependency::builder()
+                    .src_subpass(vk::SUBPASS_EXTERNAL)
+                    .dst_subpass(0)
+                    .src_stage_mask(shader_stages | attachment_stages)
+                    .dst_stage_mask(attachment_stages)
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                    .build(),
+                vk::SubpassDependency::builder()
+                    .src_subpass(0)
+                    .dst_subpass(vk::SUBPASS_EXTERNAL)
+                    .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                    .dst_stage_mask(shader_stages)
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                    .build()
+            ])
+            .build(),
+        None
+    )?;
+
+    Ok(renderpass)
+}
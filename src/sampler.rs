@@ -0,0 +1,157 @@
+pub use vk::{SamplerAddressMode, SamplerMipmapMode, CompareOp, BorderColor};
+
+use ash::vk;
+use crate::{error::VustError, Vust};
+
+/// Shared sampler handle, samplers with the same configuration are only created once per Vust and destroyed when Vust.wait_idle() shuts the render thread down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampler {
+    handle: vk::Sampler
+}
+
+impl Sampler {
+    pub fn builder() -> SamplerBuilder {
+        SamplerBuilder::default()
+    }
+
+    pub fn handle(&self) -> vk::Sampler {
+        self.handle
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerBuilder {
+    mag_filter: vk::Filter,
+    min_filter: vk::Filter,
+    mipmap_mode: vk::SamplerMipmapMode,
+    address_modes: [vk::SamplerAddressMode; 3],
+    anisotropy: Option<f32>,
+    compare_op: Option<vk::CompareOp>,
+    min_lod: f32,
+    max_lod: f32,
+    mip_lod_bias: f32,
+    border_color: vk::BorderColor
+}
+
+impl Default for SamplerBuilder {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            address_modes: [vk::SamplerAddressMode::REPEAT; 3],
+            anisotropy: None,
+            compare_op: None,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            mip_lod_bias: 0.0,
+            border_color: vk::BorderColor::INT_OPAQUE_BLACK
+        }
+    }
+}
+
+impl SamplerBuilder {
+    /// Sets the mag, min and mip filter at once
+    pub fn with_filter(mut self, filter: vk::Filter) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self.mipmap_mode = if filter == vk::Filter::LINEAR { vk::SamplerMipmapMode::LINEAR } else { vk::SamplerMipmapMode::NEAREST };
+        self
+    }
+
+    pub fn with_mag_filter(mut self, mag_filter: vk::Filter) -> Self {
+        self.mag_filter = mag_filter;
+        self
+    }
+
+    pub fn with_min_filter(mut self, min_filter: vk::Filter) -> Self {
+        self.min_filter = min_filter;
+        self
+    }
+
+    pub fn with_mipmap_mode(mut self, mipmap_mode: vk::SamplerMipmapMode) -> Self {
+        self.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    /// Same address mode for u, v and w
+    pub fn with_address_mode(mut self, address_mode: vk::SamplerAddressMode) -> Self {
+        self.address_modes = [address_mode; 3];
+        self
+    }
+
+    pub fn with_address_modes(mut self, u: vk::SamplerAddressMode, v: vk::SamplerAddressMode, w: vk::SamplerAddressMode) -> Self {
+        self.address_modes = [u, v, w];
+        self
+    }
+
+    /// Clamped to the device's max anisotropy, ignored if the device doesnt support samplerAnisotropy
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = Some(anisotropy);
+        self
+    }
+
+    /// For shadow maps, the sampled value is the result of comparing against the reference
+    pub fn with_compare_op(mut self, compare_op: vk::CompareOp) -> Self {
+        self.compare_op = Some(compare_op);
+        self
+    }
+
+    /// max_lod defaults to vk::LOD_CLAMP_NONE (every mip level)
+    pub fn with_lod_range(mut self, min_lod: f32, max_lod: f32) -> Self {
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    pub fn with_lod_bias(mut self, mip_lod_bias: f32) -> Self {
+        self.mip_lod_bias = mip_lod_bias;
+        self
+    }
+
+    /// Used by the CLAMP_TO_BORDER address mode
+    pub fn with_border_color(mut self, border_color: vk::BorderColor) -> Self {
+        self.border_color = border_color;
+        self
+    }
+
+    /// Returns the cached sampler if one with the same configuration was built before
+    pub fn build(self, vust: &Vust) -> Result<Sampler, VustError> {
+        let anisotropy = match self.anisotropy {
+            Some(anisotropy) if vust.features.sampler_anisotropy == vk::TRUE => Some(anisotropy.clamp(1.0, vust.limits.max_sampler_anisotropy)),
+            _ => None
+        };
+        let sampler_builder = Self { anisotropy, ..self };
+
+        let mut samplers = vust.samplers.lock().unwrap();
+        if let Some((_, handle)) = samplers.iter().find(|(cached, _)| *cached == sampler_builder) {
+            return Ok(Sampler { handle: *handle });
+        }
+
+        let handle = unsafe {
+            vust.device.create_sampler(
+                &vk::SamplerCreateInfo::builder()
+                    .mag_filter(self.mag_filter)
+                    .min_filter(self.min_filter)
+                    .mipmap_mode(self.mipmap_mode)
+                    .address_mode_u(self.address_modes[0])
+                    .address_mode_v(self.address_modes[1])
+                    .address_mode_w(self.address_modes[2])
+                    .anisotropy_enable(anisotropy.is_some())
+                    .max_anisotropy(anisotropy.unwrap_or(1.0))
+                    .border_color(self.border_color)
+                    .unnormalized_coordinates(false)
+                    .compare_enable(self.compare_op.is_some())
+                    .compare_op(self.compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+                    .mip_lod_bias(self.mip_lod_bias)
+                    .min_lod(self.min_lod)
+                    .max_lod(self.max_lod)
+                    .build(),
+                None
+            )?
+        };
+        samplers.push((sampler_builder, handle));
+
+        Ok(Sampler { handle })
+    }
+}
@@ -1,16 +1,23 @@
 pub use vk::{Format, Filter};
 
-use std::sync::{Arc, Mutex};
-use ash::vk::{self, Handle};
-use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, Allocator};
-use crate::{buffer::Buffer, Vust};
+use std::borrow::Cow;
+use ash::vk;
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc};
+use crate::{buffer::Buffer, error::VustError, sampler::{Sampler, SamplerBuilder}, texture_file::{self, TextureFile}, Vust};
 
 pub struct Texture {
     image: vk::Image,
+    dimensions: (u32, u32),
+    depth: u32,
+    /// texels per block and bytes per block, 1x1 for uncompressed formats
+    block: (u32, u32, u64),
+    kind: TextureKind,
+    /// array layers of the image, 6 per cube
+    layers: u32,
+    mip_levels: u32,
     allocation: Option<Allocation>,
     view: vk::ImageView,
-    sampler: vk::Sampler,
-    descriptor_info: vk::DescriptorImageInfo,
+    sampler: Sampler,
     vust: Vust
 }
 
@@ -19,10 +26,52 @@ impl Texture {
         TextureBuilder {
             #[cfg(debug_assertions)]
             name: "Default".to_string(),
-            data: &[],
+            data: Cow::Borrowed(&[]),
             dimensions: (0, 0),
+            depth: 1,
+            kind: TextureKind::Texture2D,
+            layers: 1,
+            layer_data: Vec::new(),
             format: vk::Format::R8G8B8A8_SRGB,
-            filter: vk::Filter::NEAREST
+            filter: vk::Filter::NEAREST,
+            sampler: None,
+            mipmaps: None
+        }
+    }
+
+    /// Builder with the file's format, dimensions, mip chain, array layers and kind (cubemaps become TextureKind::Cube), the sampler can still be changed
+    ///
+    /// Basis universal/supercompressed files return VustError::InvalidTextureFile
+    pub fn from_ktx2(bytes: &[u8]) -> Result<TextureBuilder<'static>, VustError> {
+        Ok(Self::from_file(texture_file::parse_ktx2(bytes)?))
+    }
+
+    /// Same as from_ktx2(), reads DXT/BCn (including DX10 headers) and 32 bit rgba files
+    pub fn from_dds(bytes: &[u8]) -> Result<TextureBuilder<'static>, VustError> {
+        Ok(Self::from_file(texture_file::parse_dds(bytes)?))
+    }
+
+    fn from_file(texture_file: TextureFile) -> TextureBuilder<'static> {
+        TextureBuilder {
+            data: Cow::Owned(texture_file.data),
+            dimensions: texture_file.dimensions,
+            depth: texture_file.depth,
+            kind: if texture_file.depth > 1 {
+                TextureKind::Texture3D
+            } else if texture_file.cube {
+                TextureKind::Cube
+            } else if texture_file.layers > 1 {
+                TextureKind::Array2D
+            } else {
+                TextureKind::Texture2D
+            },
+            layers: texture_file.layers,
+            format: texture_file.format,
+            mipmaps: Some(match texture_file.levels {
+                0 => MipGen::Auto,
+                levels => MipGen::Provided(levels)
+            }),
+            ..Self::builder()
         }
     }
 
@@ -31,23 +80,101 @@ impl Texture {
     }
 
     pub fn sampler(&self) -> vk::Sampler {
-        self.sampler
+        self.sampler.handle()
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// 1 for everything but 3d textures
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn kind(&self) -> TextureKind {
+        self.kind
+    }
+
+    /// Array layers of the image, cubes have 6 each (+X, -X, +Y, -Y, +Z, -Z)
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Same as update_layer_region() on the first layer
+    pub fn update_region(&self, offset: (u32, u32), extent: (u32, u32), data: &[u8]) -> Result<(), VustError> {
+        self.update_layer_region(0, offset, extent, data)
+    }
+
+    /// Overwrites part of the full size level of one layer (a cube face for cubes, a depth slice for 3d textures), data is tightly packed rows in the texture's format
+    ///
+    /// Compressed formats need the region aligned to their blocks (the extent can stop at the texture's edge instead). The copy is recorded at the start of the next frame (before its renderpass), frames still in flight keep sampling the old contents until then.
+    /// Other mip levels arent regenerated
+    pub fn update_layer_region(&self, layer: u32, offset: (u32, u32), extent: (u32, u32), data: &[u8]) -> Result<(), VustError> {
+        // 3d textures only have one layer, the slice is picked with the offset instead
+        let (layer, slice, layers) = match self.kind {
+            TextureKind::Texture3D => (0, layer, self.depth),
+            _ => (layer, 0, self.layers)
+        };
+        if layer.max(slice) >= layers {
+            return Err(VustError::TextureLayerOutOfBounds { layer: layer.max(slice), layers });
+        }
+        // a huge offset would wrap around and pass the check
+        let fits = |offset: u32, extent: u32, size: u32| offset.checked_add(extent).is_some_and(|end| end <= size);
+        if extent.0 == 0 || extent.1 == 0 || !fits(offset.0, extent.0, self.dimensions.0) || !fits(offset.1, extent.1, self.dimensions.1) {
+            return Err(VustError::TextureRegionOutOfBounds { offset, extent, dimensions: self.dimensions });
+        }
+        let (block_width, block_height, block_bytes) = self.block;
+        let aligned = |offset: u32, extent: u32, size: u32, block: u32| offset.is_multiple_of(block) && (extent.is_multiple_of(block) || offset + extent == size);
+        if !aligned(offset.0, extent.0, self.dimensions.0, block_width) || !aligned(offset.1, extent.1, self.dimensions.1, block_height) {
+            return Err(VustError::TextureRegionOutOfBounds { offset, extent, dimensions: self.dimensions });
+        }
+        if data.len() as u64 != extent.0.div_ceil(block_width) as u64 * extent.1.div_ceil(block_height) as u64 * block_bytes {
+            return Err(VustError::InvalidTextureData);
+        }
+
+        let staging_buffer = Buffer::builder()
+            .with_name("Texture Region Staging Buffer")
+            .with_data(data)
+            .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+            .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .build(&self.vust, true)?;
+
+        self.vust.update_texture(
+            staging_buffer.handle(),
+            self.image,
+            layer,
+            vk::Offset3D { x: offset.0 as i32, y: offset.1 as i32, z: slice as i32 },
+            vk::Extent3D { width: extent.0, height: extent.1, depth: 1 }
+        )
+        // dropping the staging buffer retires it behind the update, it lives until the copy is done
     }
 }
 
 impl Drop for Texture {
     fn drop(&mut self) {
-        self.vust.destroy_texture(self.image, self.view, self.sampler, self.allocation.take().unwrap());
+        // if the render thread is already dead theres nothing left to free it
+        let _ = self.vust.destroy_texture(self.image, self.view, self.allocation.take().unwrap());
     }
 }
 
 pub struct TextureBuilder<'a> {
     #[cfg(debug_assertions)]
     name: String,
-    data: &'a [u8],
+    data: Cow<'a, [u8]>,
     dimensions: (u32, u32),
+    depth: u32,
+    kind: TextureKind,
+    layers: u32,
+    layer_data: Vec<&'a [u8]>,
     format: vk::Format,
-    filter: vk::Filter
+    filter: vk::Filter,
+    sampler: Option<SamplerBuilder>,
+    mipmaps: Option<MipGen>
 }
 
 impl<'a> TextureBuilder<'a> {
@@ -58,12 +185,12 @@ impl<'a> TextureBuilder<'a> {
     }
 
     #[cfg(not(debug_assertions))]
-    pub fn with_name(mut self, _name: &str) -> Self {
+    pub fn with_name(self, _name: &str) -> Self {
         self
     }
 
     pub fn with_data(mut self, data: &'a [u8]) -> Self {
-        self.data = data;
+        self.data = Cow::Borrowed(data);
         self
     }
 
@@ -72,164 +199,457 @@ impl<'a> TextureBuilder<'a> {
         self
     }
 
+    /// Makes a TextureKind::Texture3D, with_data() has every slice of a level one after another, levels biggest first
+    ///
+    /// Mip levels halve the depth too
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth.max(1);
+        self.kind = TextureKind::Texture3D;
+        self
+    }
+
+    /// Defaults to TextureKind::Texture2D
+    pub fn with_kind(mut self, kind: TextureKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Array layers for Array2D (a Texture2D with more than one layer becomes an Array2D), number of cubes for Cube
+    ///
+    /// with_data() has every layer of a level one after another (cube faces in +X, -X, +Y, -Y, +Z, -Z order), levels biggest first
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers.max(1);
+        self
+    }
+
+    /// One slice per layer (per face for cubes) each with its own mip levels biggest first, used instead of with_data() and with_layers()
+    pub fn with_layer_data(mut self, layer_data: &[&'a [u8]]) -> Self {
+        self.layer_data = layer_data.to_vec();
+        self
+    }
+
     pub fn with_format(mut self, format: vk::Format) -> Self {
         self.format = format;
         self
     }
 
+    /// Shorthand for the default sampler's filters, ignored if with_sampler() is used
     pub fn with_filter(mut self, filter: vk::Filter) -> Self {
         self.filter = filter;
         self
     }
 
-    /// Returns None if data is empty
-    pub fn build(self, vust: &Vust) -> Option<Texture> {
-        if self.data.is_empty() {
-            return None;
+    /// Without this the texture gets a REPEAT sampler with with_filter()'s filter, 16x anisotropy (if supported) and its mip levels as the LOD range
+    pub fn with_sampler(mut self, sampler: SamplerBuilder) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Without this the texture only has the full size level
+    pub fn with_mipmaps(mut self, mipmaps: MipGen) -> Self {
+        self.mipmaps = Some(mipmaps);
+        self
+    }
+
+    /// Returns VustError::EmptyTextureData if data is empty and VustError::UnsupportedTextureFormat if the gpu cant sample the format (check with Vust.supports_texture_format())
+    ///
+    /// Cubes have to be square, more than one cube needs the imageCubeArray feature. 3d textures cant have layers
+    pub fn build(mut self, vust: &Vust) -> Result<Texture, VustError> {
+        let faces = if self.kind == TextureKind::Cube { 6 } else { 1 };
+        let layers = if self.layer_data.is_empty() {
+            self.layers * faces
         } else {
-            #[cfg(debug_assertions)]
-            let mut data_buffer = Buffer::builder()
-                .with_name(&self.name)
-                .with_data(self.data)
-                .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
-                .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
-                .build(vust, true);
+            if !self.layer_data.len().is_multiple_of(faces as usize) || self.layer_data.iter().any(|layer| layer.len() != self.layer_data[0].len()) {
+                return Err(VustError::InvalidTextureData);
+            }
+            self.data = Cow::Owned(self.layer_data.concat());
+            self.layer_data.len() as u32
+        };
+
+        if self.kind == TextureKind::Texture3D && layers > 1 {
+            return Err(VustError::InvalidTextureData);
+        }
+        if self.kind != TextureKind::Texture3D {
+            self.depth = 1;
+        }
+
+        if self.kind == TextureKind::Cube {
+            if self.dimensions.0 != self.dimensions.1 {
+                return Err(VustError::InvalidTextureData);
+            }
+            if layers > 6 && vust.features.image_cube_array != vk::TRUE {
+                return Err(VustError::MissingFeature("imageCubeArray"));
+            }
+        }
 
+        if self.data.is_empty() {
+            return Err(VustError::EmptyTextureData);
+        }
+
+        if !vust.supports_texture_format(self.format) {
+            return Err(VustError::UnsupportedTextureFormat(self.format));
+        }
+
+        let (mip_levels, uploaded_levels) = match self.mipmaps {
+            None => (1, 1),
+            Some(MipGen::Auto) => {
+                let features = vust.format_properties(self.format).optimal_tiling_features;
+                if !features.contains(vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+                    return Err(VustError::NoSupportedFormat("mipmap blit"));
+                }
+
+                (max_mip_levels(self.dimensions, self.depth), 1)
+            }
+            Some(MipGen::Provided(levels)) => {
+                let levels = levels.clamp(1, max_mip_levels(self.dimensions, self.depth));
+                (levels, levels)
+            }
+        };
+
+        let block = match block_size(self.format) {
+            Some(block) => block,
+            // unknown uncompressed format, the data decides the pixel size, it has to split evenly over the uploaded levels
+            None => {
+                let pixel_count = (0..uploaded_levels)
+                    .map(|level| (mip_dimensions(self.dimensions, level), mip_depth(self.depth, level)))
+                    .map(|((width, height), depth)| width as u64 * height as u64 * depth as u64 * layers as u64)
+                    .sum::<u64>();
+                if pixel_count == 0 || !(self.data.len() as u64).is_multiple_of(pixel_count) {
+                    return Err(VustError::InvalidTextureData);
+                }
+                (1, 1, self.data.len() as u64 / pixel_count)
+            }
+        };
+
+        // every layer of a level sits one after another, levels biggest first
+        let level_sizes = (0..uploaded_levels)
+            .map(|level| level_size(self.dimensions, self.depth, level, block) * layers as u64)
+            .collect::<Vec<_>>();
+        if self.data.len() as u64 != level_sizes.iter().sum::<u64>() {
+            return Err(VustError::InvalidTextureData);
+        }
+
+        // the concatenated layer data has each layer's whole mip chain together, the copy wants it level by level
+        if !self.layer_data.is_empty() && uploaded_levels > 1 {
+            let chain_size = self.layer_data[0].len();
+            let mut data = Vec::with_capacity(self.data.len());
+            let mut level_offset = 0;
+            for level_size in &level_sizes {
+                let layer_level_size = (level_size / layers as u64) as usize;
+                for layer in 0..layers as usize {
+                    let offset = layer * chain_size + level_offset;
+                    data.extend_from_slice(&self.data[offset..offset + layer_level_size]);
+                }
+                level_offset += layer_level_size;
+            }
+            self.data = Cow::Owned(data);
+        }
+
+        #[cfg(debug_assertions)]
+        let data_buffer = Buffer::builder()
+            .with_name(&self.name)
+            .with_data(&self.data)
+            .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+            .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .build(vust, true)?;
+
+        #[cfg(not(debug_assertions))]
+        let data_buffer = Buffer::builder()
+            .with_data(&self.data)
+            .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+            .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .build(vust, true)?;
+
+        unsafe {
+            let image = vust.device.create_image(
+                &vk::ImageCreateInfo::builder()
+                    .image_type(if self.kind == TextureKind::Texture3D { vk::ImageType::TYPE_3D } else { vk::ImageType::TYPE_2D })
+                    .flags(if self.kind == TextureKind::Cube { vk::ImageCreateFlags::CUBE_COMPATIBLE } else { vk::ImageCreateFlags::empty() })
+                    .extent(
+                        vk::Extent3D {
+                            width: self.dimensions.0,
+                            height: self.dimensions.1,
+                            depth: self.depth
+                        }
+                    )
+                    .mip_levels(mip_levels)
+                    .array_layers(layers)
+                    .format(self.format)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    // transfer src to blit the mips from
+                    .usage(vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .build(),
+                None
+            )?;
+
+            let requirements = vust.device.get_image_memory_requirements(image);
+
+            #[cfg(debug_assertions)]
+            let name = &self.name;
             #[cfg(not(debug_assertions))]
-            let mut data_buffer = Buffer::builder()
-                .with_data(self.data)
-                .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
-                .with_usage(vk::BufferUsageFlags::TRANSFER_SRC)
-                .build(vust, true);
-
-            unsafe {
-                let image = vust.device.create_image(
-                    &vk::ImageCreateInfo::builder()
-                        .image_type(vk::ImageType::TYPE_2D)
-                        .extent(
-                            vk::Extent3D {
-                                width: self.dimensions.0,
-                                height: self.dimensions.1,
-                                depth: 1
-                            }
-                        )
-                        .mip_levels(1)
-                        .array_layers(1)
-                        .format(self.format)
-                        .tiling(vk::ImageTiling::OPTIMAL)
-                        .initial_layout(vk::ImageLayout::UNDEFINED)
-                        .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
-                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
-                        .samples(vk::SampleCountFlags::TYPE_1)
-                        .build(),
-                    None
-                ).unwrap();
-
-                let requirements = vust.device.get_image_memory_requirements(image);
-
-                #[cfg(debug_assertions)]
-                let name = &self.name;
-                #[cfg(not(debug_assertions))]
-                let name = "texture";
-
-                let allocation = vust.memory_allocator.lock().unwrap().allocate(
-                    &AllocationCreateDesc {
-                        name,
-                        requirements,
-                        location: gpu_allocator::MemoryLocation::GpuOnly,
-                        linear: true,
-                        allocation_scheme: gpu_allocator::vulkan::AllocationScheme::GpuAllocatorManaged
-                    }
-                ).unwrap();
-
-                vust.device.bind_image_memory(image, allocation.memory(), allocation.offset()).unwrap();
-
-                vust.transition_image_layout(image, vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
-                let copy_command_buffer = vust.begin_single_exec_command();
-                let regions = [
-                    vk::BufferImageCopy::builder()
-                        .buffer_offset(0)
-                        .buffer_row_length(0)
-                        .buffer_image_height(0)
-                        .image_subresource(
-                            vk::ImageSubresourceLayers::builder()
-                                .aspect_mask(vk::ImageAspectFlags::COLOR)
-                                .mip_level(0)
-                                .base_array_layer(0)
-                                .layer_count(1)
-                                .build()
-                        )
-                        .image_offset(
-                            vk::Offset3D { x: 0, y: 0, z: 0 }
-                        )
-                        .image_extent(
-                            vk::Extent3D { width: self.dimensions.0, height: self.dimensions.1, depth: 1 }
-                        )
-                        .build()
-                ];
-                vust.device.cmd_copy_buffer_to_image(
-                    copy_command_buffer,
-                    data_buffer.handle(),
-                    image,
-                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    &regions
-                );
-                vust.end_single_exec_command(copy_command_buffer);
-                vust.transition_image_layout(image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
-
-                let view = vust.device.create_image_view(
-                    &vk::ImageViewCreateInfo::builder()
-                        .image(image)
-                        .view_type(vk::ImageViewType::TYPE_2D)
-                        .format(self.format)
-                        .subresource_range(
-                            vk::ImageSubresourceRange::builder()
-                                .aspect_mask(vk::ImageAspectFlags::COLOR)
-                                .base_mip_level(0)
-                                .level_count(1)
-                                .base_array_layer(0)
-                                .layer_count(1)
-                                .build()
-                        )
-                        .build(),
-                    None
-                ).unwrap();
-
-                let sampler = vust.device.create_sampler(
-                    &vk::SamplerCreateInfo::builder()
-                        .mag_filter(self.filter)
-                        .min_filter(self.filter)
-                        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-                        .address_mode_u(vk::SamplerAddressMode::REPEAT)
-                        .address_mode_v(vk::SamplerAddressMode::REPEAT)
-                        .address_mode_w(vk::SamplerAddressMode::REPEAT)
-                        .anisotropy_enable(true)
-                        .max_anisotropy(16.0)
-                        .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
-                        .unnormalized_coordinates(false)
-                        .compare_enable(false)
-                        .mip_lod_bias(0.0)
-                        .min_lod(0.0)
-                        .max_lod(0.0)
-                        .build(),
-                    None
-                ).unwrap();
-
-                let descriptor_info = vk::DescriptorImageInfo::builder()
-                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                    .image_view(view)
-                    .sampler(sampler)
+            let name = "texture";
+
+            let allocation = vust.memory_allocator.lock().unwrap().allocate(
+                &AllocationCreateDesc {
+                    name,
+                    requirements,
+                    location: gpu_allocator::MemoryLocation::GpuOnly,
+                    linear: true,
+                    allocation_scheme: gpu_allocator::vulkan::AllocationScheme::GpuAllocatorManaged
+                }
+            )?;
+
+            vust.device.bind_image_memory(image, allocation.memory(), allocation.offset())?;
+
+            let copy_command_buffer = vust.begin_single_exec_command()?;
+            image_barrier(&vust.device, copy_command_buffer, image, 0..mip_levels, 0..layers, vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+
+            // provided levels sit one after another in the data, biggest first
+            let mut buffer_offset = 0;
+            let regions = (0..uploaded_levels).map(|level| {
+                let (width, height) = mip_dimensions(self.dimensions, level);
+                let region = vk::BufferImageCopy::builder()
+                    .buffer_offset(buffer_offset)
+                    .buffer_row_length(0)
+                    .buffer_image_height(0)
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .mip_level(level)
+                            .base_array_layer(0)
+                            .layer_count(layers)
+                            .build()
+                    )
+                    .image_offset(
+                        vk::Offset3D { x: 0, y: 0, z: 0 }
+                    )
+                    .image_extent(
+                        vk::Extent3D { width, height, depth: mip_depth(self.depth, level) }
+                    )
                     .build();
 
-                Some(Texture {
-                    image,
-                    allocation: Some(allocation),
-                    view,
-                    sampler,
-                    descriptor_info,
-                    vust: vust.clone()
-                })
+                buffer_offset += level_sizes[level as usize];
+                region
+            }).collect::<Vec<_>>();
+            vust.device.cmd_copy_buffer_to_image(
+                copy_command_buffer,
+                data_buffer.handle(),
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions
+            );
+
+            if let Some(MipGen::Auto) = self.mipmaps {
+                // every level is blitted down from the one before it, which is done after that
+                for level in 1..mip_levels {
+                    image_barrier(&vust.device, copy_command_buffer, image, level - 1..level, 0..layers, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+
+                    let (src_width, src_height) = mip_dimensions(self.dimensions, level - 1);
+                    let (dst_width, dst_height) = mip_dimensions(self.dimensions, level);
+                    let (src_depth, dst_depth) = (mip_depth(self.depth, level - 1), mip_depth(self.depth, level));
+                    vust.device.cmd_blit_image(
+                        copy_command_buffer,
+                        image,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &[vk::ImageBlit::builder()
+                            .src_subresource(vk::ImageSubresourceLayers { aspect_mask: vk::ImageAspectFlags::COLOR, mip_level: level - 1, base_array_layer: 0, layer_count: layers })
+                            .src_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, vk::Offset3D { x: src_width as i32, y: src_height as i32, z: src_depth as i32 }])
+                            .dst_subresource(vk::ImageSubresourceLayers { aspect_mask: vk::ImageAspectFlags::COLOR, mip_level: level, base_array_layer: 0, layer_count: layers })
+                            .dst_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, vk::Offset3D { x: dst_width as i32, y: dst_height as i32, z: dst_depth as i32 }])
+                            .build()],
+                        vk::Filter::LINEAR
+                    );
+
+                    image_barrier(&vust.device, copy_command_buffer, image, level - 1..level, 0..layers, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+                }
+                image_barrier(&vust.device, copy_command_buffer, image, mip_levels - 1..mip_levels, 0..layers, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+            } else {
+                image_barrier(&vust.device, copy_command_buffer, image, 0..mip_levels, 0..layers, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
             }
+            vust.end_single_exec_command(copy_command_buffer)?;
+
+            let view = vust.device.create_image_view(
+                &vk::ImageViewCreateInfo::builder()
+                    .image(image)
+                    .view_type(match self.kind {
+                        TextureKind::Texture2D if layers == 1 => vk::ImageViewType::TYPE_2D,
+                        TextureKind::Texture2D | TextureKind::Array2D => vk::ImageViewType::TYPE_2D_ARRAY,
+                        TextureKind::Cube if layers == 6 => vk::ImageViewType::CUBE,
+                        TextureKind::Cube => vk::ImageViewType::CUBE_ARRAY,
+                        TextureKind::Texture3D => vk::ImageViewType::TYPE_3D
+                    })
+                    .format(self.format)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(mip_levels)
+                            .base_array_layer(0)
+                            .layer_count(layers)
+                            .build()
+                    )
+                    .build(),
+                None
+            )?;
+
+            let sampler = match self.sampler {
+                Some(sampler_builder) => sampler_builder,
+                None => Sampler::builder()
+                    .with_filter(self.filter)
+                    .with_anisotropy(16.0)
+                    .with_lod_range(0.0, mip_levels as f32)
+            }.build(vust)?;
+
+            Ok(Texture {
+                image,
+                dimensions: self.dimensions,
+                depth: self.depth,
+                block,
+                kind: if self.kind == TextureKind::Texture2D && layers > 1 { TextureKind::Array2D } else { self.kind },
+                layers,
+                mip_levels,
+                allocation: Some(allocation),
+                view,
+                sampler,
+                vust: vust.clone()
+            })
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureKind {
+    /// single layer, sampler2D
+    Texture2D,
+    /// sampler2DArray, for sprite sheets and splat maps
+    Array2D,
+    /// six square faces, samplerCube (samplerCubeArray with more than one cube)
+    Cube,
+    /// volume made with with_depth(), sampler3D
+    Texture3D
+}
+
+pub enum MipGen {
+    /// full chain down to 1x1, blitted from the uploaded image on the gpu
+    Auto,
+    /// the data has this many levels one after another, biggest first
+    Provided(u32)
+}
+
+/// Levels down to 1x1(x1)
+pub(crate) fn max_mip_levels(dimensions: (u32, u32), depth: u32) -> u32 {
+    32 - dimensions.0.max(dimensions.1).max(depth).max(1).leading_zeros()
+}
+
+pub(crate) fn mip_dimensions(dimensions: (u32, u32), level: u32) -> (u32, u32) {
+    ((dimensions.0 >> level).max(1), (dimensions.1 >> level).max(1))
+}
+
+pub(crate) fn mip_depth(depth: u32, level: u32) -> u32 {
+    (depth >> level).max(1)
+}
+
+/// Records a color layout transition for the given mip levels and layers, access masks and stages are picked from the layouts
+pub(crate) fn image_barrier(device: &ash::Device, command_buffer: vk::CommandBuffer, image: vk::Image, mip_levels: std::ops::Range<u32>, layers: std::ops::Range<u32>, old_layout: vk::ImageLayout, new_layout: vk::ImageLayout) {
+    let access_and_stage = |layout| match layout {
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => (vk::AccessFlags::TRANSFER_WRITE, vk::PipelineStageFlags::TRANSFER),
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (vk::AccessFlags::TRANSFER_READ, vk::PipelineStageFlags::TRANSFER),
+        // textures can be sampled in the vertex shader too
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (vk::AccessFlags::SHADER_READ, vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER),
+        _ => (vk::AccessFlags::empty(), vk::PipelineStageFlags::TOP_OF_PIPE)
+    };
+    let (src_access, src_stage) = access_and_stage(old_layout);
+    let (dst_access, dst_stage) = access_and_stage(new_layout);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage,
+            dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[vk::ImageMemoryBarrier::builder()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: mip_levels.start,
+                    level_count: mip_levels.end - mip_levels.start,
+                    base_array_layer: layers.start,
+                    layer_count: layers.end - layers.start
+                })
+                .build()]
+        );
+    }
+}
+
+/// Bytes one layer of the mip level takes up (every slice for 3d textures), block is from block_size()
+pub(crate) fn level_size(dimensions: (u32, u32), depth: u32, level: u32, block: (u32, u32, u64)) -> u64 {
+    let (width, height) = mip_dimensions(dimensions, level);
+    width.div_ceil(block.0) as u64 * height.div_ceil(block.1) as u64 * mip_depth(depth, level) as u64 * block.2
+}
+
+/// Block width, height and bytes for the format, uncompressed formats have 1x1 blocks
+///
+/// None for formats vust doesnt know the size of
+pub(crate) fn block_size(format: vk::Format) -> Option<(u32, u32, u64)> {
+    use vk::Format as F;
+
+    Some(match format {
+        F::R8_UNORM | F::R8_SNORM | F::R8_UINT | F::R8_SINT | F::R8_SRGB => (1, 1, 1),
+        F::R8G8_UNORM | F::R8G8_SNORM | F::R8G8_UINT | F::R8G8_SINT | F::R8G8_SRGB
+            | F::R16_UNORM | F::R16_SNORM | F::R16_UINT | F::R16_SINT | F::R16_SFLOAT
+            | F::R5G6B5_UNORM_PACK16 | F::B5G6R5_UNORM_PACK16 | F::R4G4B4A4_UNORM_PACK16 | F::B4G4R4A4_UNORM_PACK16
+            | F::R5G5B5A1_UNORM_PACK16 | F::B5G5R5A1_UNORM_PACK16 | F::A1R5G5B5_UNORM_PACK16 => (1, 1, 2),
+        F::R8G8B8_UNORM | F::R8G8B8_SRGB | F::B8G8R8_UNORM | F::B8G8R8_SRGB => (1, 1, 3),
+        F::R8G8B8A8_UNORM | F::R8G8B8A8_SNORM | F::R8G8B8A8_UINT | F::R8G8B8A8_SINT | F::R8G8B8A8_SRGB
+            | F::B8G8R8A8_UNORM | F::B8G8R8A8_SNORM | F::B8G8R8A8_UINT | F::B8G8R8A8_SINT | F::B8G8R8A8_SRGB
+            | F::A8B8G8R8_UNORM_PACK32 | F::A8B8G8R8_SRGB_PACK32 | F::A2R10G10B10_UNORM_PACK32 | F::A2B10G10R10_UNORM_PACK32
+            | F::R16G16_UNORM | F::R16G16_SNORM | F::R16G16_UINT | F::R16G16_SINT | F::R16G16_SFLOAT
+            | F::R32_UINT | F::R32_SINT | F::R32_SFLOAT | F::B10G11R11_UFLOAT_PACK32 | F::E5B9G9R9_UFLOAT_PACK32 => (1, 1, 4),
+        F::R16G16B16_UNORM | F::R16G16B16_SFLOAT => (1, 1, 6),
+        F::R16G16B16A16_UNORM | F::R16G16B16A16_SNORM | F::R16G16B16A16_UINT | F::R16G16B16A16_SINT | F::R16G16B16A16_SFLOAT
+            | F::R32G32_UINT | F::R32G32_SINT | F::R32G32_SFLOAT => (1, 1, 8),
+        F::R32G32B32_UINT | F::R32G32B32_SINT | F::R32G32B32_SFLOAT => (1, 1, 12),
+        F::R32G32B32A32_UINT | F::R32G32B32A32_SINT | F::R32G32B32A32_SFLOAT => (1, 1, 16),
+
+        F::BC1_RGB_UNORM_BLOCK | F::BC1_RGB_SRGB_BLOCK | F::BC1_RGBA_UNORM_BLOCK | F::BC1_RGBA_SRGB_BLOCK
+            | F::BC4_UNORM_BLOCK | F::BC4_SNORM_BLOCK => (4, 4, 8),
+        F::BC2_UNORM_BLOCK | F::BC2_SRGB_BLOCK | F::BC3_UNORM_BLOCK | F::BC3_SRGB_BLOCK
+            | F::BC5_UNORM_BLOCK | F::BC5_SNORM_BLOCK | F::BC6H_UFLOAT_BLOCK | F::BC6H_SFLOAT_BLOCK
+            | F::BC7_UNORM_BLOCK | F::BC7_SRGB_BLOCK => (4, 4, 16),
+
+        F::ETC2_R8G8B8_UNORM_BLOCK | F::ETC2_R8G8B8_SRGB_BLOCK | F::ETC2_R8G8B8A1_UNORM_BLOCK | F::ETC2_R8G8B8A1_SRGB_BLOCK
+            | F::EAC_R11_UNORM_BLOCK | F::EAC_R11_SNORM_BLOCK => (4, 4, 8),
+        F::ETC2_R8G8B8A8_UNORM_BLOCK | F::ETC2_R8G8B8A8_SRGB_BLOCK | F::EAC_R11G11_UNORM_BLOCK | F::EAC_R11G11_SNORM_BLOCK => (4, 4, 16),
+
+        // every astc block is 16 bytes
+        F::ASTC_4X4_UNORM_BLOCK | F::ASTC_4X4_SRGB_BLOCK => (4, 4, 16),
+        F::ASTC_5X4_UNORM_BLOCK | F::ASTC_5X4_SRGB_BLOCK => (5, 4, 16),
+        F::ASTC_5X5_UNORM_BLOCK | F::ASTC_5X5_SRGB_BLOCK => (5, 5, 16),
+        F::ASTC_6X5_UNORM_BLOCK | F::ASTC_6X5_SRGB_BLOCK => (6, 5, 16),
+        F::ASTC_6X6_UNORM_BLOCK | F::ASTC_6X6_SRGB_BLOCK => (6, 6, 16),
+        F::ASTC_8X5_UNORM_BLOCK | F::ASTC_8X5_SRGB_BLOCK => (8, 5, 16),
+        F::ASTC_8X6_UNORM_BLOCK | F::ASTC_8X6_SRGB_BLOCK => (8, 6, 16),
+        F::ASTC_8X8_UNORM_BLOCK | F::ASTC_8X8_SRGB_BLOCK => (8, 8, 16),
+        F::ASTC_10X5_UNORM_BLOCK | F::ASTC_10X5_SRGB_BLOCK => (10, 5, 16),
+        F::ASTC_10X6_UNORM_BLOCK | F::ASTC_10X6_SRGB_BLOCK => (10, 6, 16),
+        F::ASTC_10X8_UNORM_BLOCK | F::ASTC_10X8_SRGB_BLOCK => (10, 8, 16),
+        F::ASTC_10X10_UNORM_BLOCK | F::ASTC_10X10_SRGB_BLOCK => (10, 10, 16),
+        F::ASTC_12X10_UNORM_BLOCK | F::ASTC_12X10_SRGB_BLOCK => (12, 10, 16),
+        F::ASTC_12X12_UNORM_BLOCK | F::ASTC_12X12_SRGB_BLOCK => (12, 12, 16),
+
+        _ => return None
+    })
+}
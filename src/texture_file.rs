@@ -0,0 +1,249 @@
+use ash::vk;
+use crate::{error::VustError, texture};
+
+/// Texture file parsed into what TextureBuilder wants, data is every level one after another (biggest first) with each level's layers (or slices) one after another
+pub(crate) struct TextureFile {
+    pub(crate) format: vk::Format,
+    pub(crate) dimensions: (u32, u32),
+    /// more than 1 is a 3d texture
+    pub(crate) depth: u32,
+    /// array layers, not counting cube faces
+    pub(crate) layers: u32,
+    /// layers are cubes with 6 faces each
+    pub(crate) cube: bool,
+    /// 0 means the file wants the mips generated
+    pub(crate) levels: u32,
+    pub(crate) data: Vec<u8>
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+/// identifier + 9 u32s + the dfd/kvd/sgd index
+const KTX2_LEVEL_INDEX_OFFSET: usize = 80;
+
+const DDS_MAGIC: u32 = 0x20534444; // "DDS "
+const DDS_HEADER_SIZE: usize = 4 + 124;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+
+pub(crate) fn parse_ktx2(bytes: &[u8]) -> Result<TextureFile, VustError> {
+    if bytes.get(..12) != Some(&KTX2_IDENTIFIER[..]) {
+        return Err(VustError::InvalidTextureFile("not a ktx2 file"));
+    }
+
+    let format = vk::Format::from_raw(read_u32(bytes, 12)? as i32);
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?.max(1);
+    let depth = read_u32(bytes, 28)?.max(1);
+    let layers = read_u32(bytes, 32)?.max(1);
+    let faces = read_u32(bytes, 36)?;
+    let levels = read_u32(bytes, 40)?;
+    let supercompression = read_u32(bytes, 44)?;
+
+    if width == 0 {
+        return Err(VustError::InvalidTextureFile("width cant be 0"));
+    }
+    if format == vk::Format::UNDEFINED || supercompression != 0 {
+        return Err(VustError::InvalidTextureFile("basis universal and supercompressed ktx2 files arent supported"));
+    }
+    if depth > 1 && (layers > 1 || faces > 1) {
+        return Err(VustError::InvalidTextureFile("3d textures cant have layers or faces"));
+    }
+    if faces != 1 && faces != 6 {
+        return Err(VustError::InvalidTextureFile("face count has to be 1 or 6"));
+    }
+    if levels > texture::max_mip_levels((width, height), depth) {
+        return Err(VustError::InvalidTextureFile("more mip levels than the dimensions allow"));
+    }
+    let block = texture::block_size(format).ok_or(VustError::InvalidTextureFile("unknown vk format"))?;
+    let images = layers.checked_mul(faces).ok_or(VustError::InvalidTextureFile("too many layers"))?;
+
+    // levels already hold every layer and face in the order vulkan wants them, just put the levels biggest first
+    let mut data = Vec::new();
+    for level in 0..levels.max(1) {
+        let index = KTX2_LEVEL_INDEX_OFFSET + level as usize * 24;
+        let offset = read_u64(bytes, index)? as usize;
+        let length = read_u64(bytes, index + 8)? as usize;
+
+        if length as u64 != checked_level_size((width, height), depth, level, block, images)? {
+            return Err(VustError::InvalidTextureFile("level size doesnt match the format and dimensions"));
+        }
+
+        let end = offset.checked_add(length).ok_or(VustError::InvalidTextureFile("level data is cut off"))?;
+        data.extend_from_slice(bytes.get(offset..end).ok_or(VustError::InvalidTextureFile("level data is cut off"))?);
+    }
+
+    Ok(TextureFile {
+        format,
+        dimensions: (width, height),
+        depth,
+        layers,
+        cube: faces == 6,
+        levels,
+        data
+    })
+}
+
+pub(crate) fn parse_dds(bytes: &[u8]) -> Result<TextureFile, VustError> {
+    if read_u32(bytes, 0)? != DDS_MAGIC || read_u32(bytes, 4)? != 124 {
+        return Err(VustError::InvalidTextureFile("not a dds file"));
+    }
+
+    let flags = read_u32(bytes, 8)?;
+    let height = read_u32(bytes, 12)?.max(1);
+    let width = read_u32(bytes, 16)?;
+    let mip_map_count = read_u32(bytes, 28)?;
+    let pixel_format_flags = read_u32(bytes, 80)?;
+    let four_cc = bytes.get(84..88).ok_or(VustError::InvalidTextureFile("header is cut off"))?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let mut depth = if caps2 & DDSCAPS2_VOLUME != 0 { read_u32(bytes, 24)?.max(1) } else { 1 };
+
+    if width == 0 {
+        return Err(VustError::InvalidTextureFile("width cant be 0"));
+    }
+
+    let levels = if flags & DDSD_MIPMAPCOUNT != 0 { mip_map_count.max(1) } else { 1 };
+    // older files only mark cubemaps in caps2, partial cubemaps arent supported
+    let mut faces = if caps2 & DDSCAPS2_CUBEMAP != 0 { 6 } else { 1 };
+    let mut layers = 1;
+    let mut data_offset = DDS_HEADER_SIZE;
+
+    let format = if pixel_format_flags & DDPF_FOURCC != 0 {
+        match four_cc {
+            b"DXT1" => vk::Format::BC1_RGBA_UNORM_BLOCK,
+            b"DXT2" | b"DXT3" => vk::Format::BC2_UNORM_BLOCK,
+            b"DXT4" | b"DXT5" => vk::Format::BC3_UNORM_BLOCK,
+            b"ATI1" | b"BC4U" => vk::Format::BC4_UNORM_BLOCK,
+            b"BC4S" => vk::Format::BC4_SNORM_BLOCK,
+            b"ATI2" | b"BC5U" => vk::Format::BC5_UNORM_BLOCK,
+            b"BC5S" => vk::Format::BC5_SNORM_BLOCK,
+            b"DX10" => {
+                let dxgi_format = read_u32(bytes, DDS_HEADER_SIZE)?;
+                let resource_dimension = read_u32(bytes, DDS_HEADER_SIZE + 4)?;
+                let misc_flag = read_u32(bytes, DDS_HEADER_SIZE + 8)?;
+
+                if resource_dimension == D3D10_RESOURCE_DIMENSION_TEXTURE3D {
+                    depth = read_u32(bytes, 24)?.max(1);
+                }
+                if misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0 {
+                    faces = 6;
+                }
+                layers = read_u32(bytes, DDS_HEADER_SIZE + 12)?.max(1);
+                data_offset += DDS_DX10_HEADER_SIZE;
+
+                dxgi_to_vk_format(dxgi_format).ok_or(VustError::InvalidTextureFile("unsupported dxgi format"))?
+            }
+            _ => return Err(VustError::InvalidTextureFile("unsupported fourcc"))
+        }
+    } else if pixel_format_flags & DDPF_RGB != 0 && read_u32(bytes, 88)? == 32 {
+        match (read_u32(bytes, 92)?, read_u32(bytes, 100)?) {
+            (0x000000FF, 0x00FF0000) => vk::Format::R8G8B8A8_UNORM,
+            (0x00FF0000, 0x000000FF) => vk::Format::B8G8R8A8_UNORM,
+            _ => return Err(VustError::InvalidTextureFile("unsupported rgb channel masks"))
+        }
+    } else {
+        return Err(VustError::InvalidTextureFile("unsupported pixel format"));
+    };
+    let block = texture::block_size(format).ok_or(VustError::InvalidTextureFile("unsupported pixel format"))?;
+    let images = layers.checked_mul(faces).ok_or(VustError::InvalidTextureFile("too many layers"))?;
+    if depth > 1 && images > 1 {
+        return Err(VustError::InvalidTextureFile("3d textures cant have layers or faces"));
+    }
+    if levels > texture::max_mip_levels((width, height), depth) {
+        return Err(VustError::InvalidTextureFile("more mip levels than the dimensions allow"));
+    }
+
+    // volumes have every slice of a level together already
+    let level_sizes = (0..levels)
+        .map(|level| checked_level_size((width, height), depth, level, block, 1).map(|size| size as usize))
+        .collect::<Result<Vec<_>, _>>()?;
+    let chain_size = level_sizes.iter().try_fold(0usize, |sum, size| sum.checked_add(*size)).ok_or(VustError::InvalidTextureFile("texture is too big"))?;
+    let images = images as usize;
+
+    let end = chain_size.checked_mul(images)
+        .and_then(|body_size| body_size.checked_add(data_offset))
+        .ok_or(VustError::InvalidTextureFile("texture is too big"))?;
+    let body = bytes.get(data_offset..end).ok_or(VustError::InvalidTextureFile("image data is cut off"))?;
+
+    // dds stores every layer's whole mip chain one after another, reorder it to level by level
+    let mut data = Vec::with_capacity(body.len());
+    let mut level_offset = 0;
+    for level_size in level_sizes {
+        for image in 0..images {
+            let offset = image * chain_size + level_offset;
+            data.extend_from_slice(&body[offset..offset + level_size]);
+        }
+        level_offset += level_size;
+    }
+
+    Ok(TextureFile {
+        format,
+        dimensions: (width, height),
+        depth,
+        layers,
+        cube: faces == 6,
+        levels,
+        data
+    })
+}
+
+fn dxgi_to_vk_format(dxgi_format: u32) -> Option<vk::Format> {
+    Some(match dxgi_format {
+        2 => vk::Format::R32G32B32A32_SFLOAT,
+        10 => vk::Format::R16G16B16A16_SFLOAT,
+        24 => vk::Format::A2B10G10R10_UNORM_PACK32,
+        26 => vk::Format::B10G11R11_UFLOAT_PACK32,
+        28 => vk::Format::R8G8B8A8_UNORM,
+        29 => vk::Format::R8G8B8A8_SRGB,
+        41 => vk::Format::R32_SFLOAT,
+        49 => vk::Format::R8G8_UNORM,
+        54 => vk::Format::R16_SFLOAT,
+        61 => vk::Format::R8_UNORM,
+        71 => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        72 => vk::Format::BC1_RGBA_SRGB_BLOCK,
+        74 => vk::Format::BC2_UNORM_BLOCK,
+        75 => vk::Format::BC2_SRGB_BLOCK,
+        77 => vk::Format::BC3_UNORM_BLOCK,
+        78 => vk::Format::BC3_SRGB_BLOCK,
+        80 => vk::Format::BC4_UNORM_BLOCK,
+        81 => vk::Format::BC4_SNORM_BLOCK,
+        83 => vk::Format::BC5_UNORM_BLOCK,
+        84 => vk::Format::BC5_SNORM_BLOCK,
+        87 => vk::Format::B8G8R8A8_UNORM,
+        91 => vk::Format::B8G8R8A8_SRGB,
+        95 => vk::Format::BC6H_UFLOAT_BLOCK,
+        96 => vk::Format::BC6H_SFLOAT_BLOCK,
+        98 => vk::Format::BC7_UNORM_BLOCK,
+        99 => vk::Format::BC7_SRGB_BLOCK,
+        _ => return None
+    })
+}
+
+/// texture::level_size() times the images per level, the sizes come from the file so they can overflow
+fn checked_level_size(dimensions: (u32, u32), depth: u32, level: u32, block: (u32, u32, u64), images: u32) -> Result<u64, VustError> {
+    let (width, height) = texture::mip_dimensions(dimensions, level);
+    (width.div_ceil(block.0) as u64)
+        .checked_mul(height.div_ceil(block.1) as u64)
+        .and_then(|size| size.checked_mul(texture::mip_depth(depth, level) as u64))
+        .and_then(|size| size.checked_mul(block.2))
+        .and_then(|size| size.checked_mul(images as u64))
+        .ok_or(VustError::InvalidTextureFile("texture is too big"))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, VustError> {
+    bytes.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(VustError::InvalidTextureFile("header is cut off"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, VustError> {
+    bytes.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(VustError::InvalidTextureFile("header is cut off"))
+}
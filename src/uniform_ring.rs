@@ -0,0 +1,92 @@
+use ash::vk;
+use bytemuck::Pod;
+use crate::{buffer::Buffer, error::VustError, write_descriptor_info::WriteDescriptorInfo, Vust};
+
+/// One big host visible uniform buffer split into a region per frame in flight, uniforms are allocated linearly inside the current frame's region
+///
+/// Bind it to a UNIFORM_BUFFER_DYNAMIC binding once, then give every draw its offset with Vust.bind_descriptor_set_with_offsets()
+///
+/// ``` ignore
+/// vust.reset_command_buffer()?;
+/// vust_syncer.sync()?;
+/// uniform_ring.begin_frame();
+/// for model in models {
+///     let (offset, _) = uniform_ring.allocate(&model)?;
+///     vust.bind_descriptor_set_with_offsets(pipeline.pipeline_layout(), &descriptor, &[offset])?;
+///     vust.draw(3)?;
+/// }
+/// ```
+pub struct UniformRing {
+    buffer: Buffer,
+    /// bytes per frame in flight
+    frame_size: u64,
+    alignment: u64,
+    frame: usize,
+    head: u64,
+    vust: Vust
+}
+
+impl UniformRing {
+    /// frame_size is how many bytes one frame can allocate, it gets rounded up to the device's uniform offset alignment
+    pub fn new(vust: &Vust, frame_size: u64) -> Result<Self, VustError> {
+        let alignment = vust.limits.min_uniform_buffer_offset_alignment.max(1);
+        let frame_size = frame_size.max(1).next_multiple_of(alignment);
+
+        let buffer = Buffer::builder::<u8>()
+            .with_name("Uniform Ring Buffer")
+            .with_capacity((frame_size * vust.frames_in_flight() as u64) as usize)
+            .with_usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
+            .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+            .build(vust, false)?;
+
+        Ok(Self {
+            buffer,
+            frame_size,
+            alignment,
+            frame: 0,
+            head: 0,
+            vust: vust.clone()
+        })
+    }
+
+    /// Always run right after VustSyncer.sync(), everything allocated in this frame's region before is free again
+    pub fn begin_frame(&mut self) {
+        self.frame = self.vust.current_frame();
+        self.head = 0;
+    }
+
+    /// Copies the uniform into the current frame's region, returns the dynamic offset and size in bytes
+    ///
+    /// Returns VustError::BufferOverflow when the frame has no room left
+    pub fn allocate<T: Pod>(&mut self, data: &T) -> Result<(u32, u64), VustError> {
+        self.allocate_slice(std::slice::from_ref(data))
+    }
+
+    pub fn allocate_slice<T: Pod>(&mut self, data: &[T]) -> Result<(u32, u64), VustError> {
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        let size = bytes.len() as u64;
+
+        if self.head + size > self.frame_size {
+            return Err(VustError::BufferOverflow { data_size: self.head + size, buffer_size: self.frame_size });
+        }
+
+        let offset = self.frame as u64 * self.frame_size + self.head;
+        self.buffer.overwrite_offset(bytes, offset as usize)?;
+        self.head = (self.head + size).next_multiple_of(self.alignment);
+
+        Ok((offset as u32, size))
+    }
+
+    /// For the UNIFORM_BUFFER_DYNAMIC binding, range is the size of one uniform
+    pub fn write_descriptor_info(&self, range: u64) -> WriteDescriptorInfo {
+        WriteDescriptorInfo::Buffer {
+            buffer: self.buffer.handle(),
+            offset: 0,
+            range
+        }
+    }
+
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer.handle()
+    }
+}
@@ -1,6 +1,7 @@
+use std::sync::mpsc;
 use ash::vk;
 use gpu_allocator::vulkan::Allocation;
-use crate::{descriptor::Descriptor, write_descriptor_info::WriteDescriptorInfo};
+use crate::{capture::CapturedImage, error::VustError, create_info::LoadOp, descriptor::Descriptor, render_graph::CompiledRenderGraph, write_descriptor_info::WriteDescriptorInfo};
 
 pub enum VustCommand {
     KYS, // kill yourself
@@ -12,9 +13,65 @@ pub enum VustCommand {
     DestroyTexture {
         image: vk::Image,
         view: vk::ImageView,
-        sampler: vk::Sampler,
         allocation: Allocation
     },
+    /// images go through DestroyTexture
+    DestroyRenderTarget {
+        renderpass: vk::RenderPass,
+        framebuffer: vk::Framebuffer
+    },
+
+    Resize {
+        width: u32,
+        height: u32
+    },
+
+    SetVsync {
+        vsync: bool
+    },
+
+    SetClearColor {
+        color: [f32; 4]
+    },
+    SetClearDepthStencil {
+        depth: f32,
+        stencil: u32
+    },
+    SetLoadOp {
+        load_op: LoadOp
+    },
+
+    ReadBuffer {
+        buffer: vk::Buffer,
+        size: vk::DeviceSize,
+        callback: ReadCallback
+    },
+    /// same as ReadBuffer but submitted on its own right away
+    ReadBufferNow {
+        buffer: vk::Buffer,
+        size: vk::DeviceSize,
+        callback: ReadCallback
+    },
+
+    /// recorded at the start of the next frame, before its renderpass
+    UpdateTexture {
+        staging_buffer: vk::Buffer,
+        image: vk::Image,
+        layer: u32,
+        offset: vk::Offset3D,
+        extent: vk::Extent3D
+    },
+
+    /// submitted right away with the fence, the sender gets the submit result
+    SubmitSingleExec {
+        command_buffer: vk::CommandBuffer,
+        fence: vk::Fence,
+        sender: mpsc::Sender<Result<(), VustError>>
+    },
+
+    CaptureFrame {
+        sender: mpsc::Sender<CapturedImage>
+    },
 
     ResetCommandBuffer,
     BindPipeline {
@@ -26,9 +83,15 @@ pub enum VustCommand {
     BindScissor {
         scissor: vk::Rect2D
     },
+    SetStencilReference {
+        face_mask: vk::StencilFaceFlags,
+        reference: u32
+    },
     BindDescriptorSet {
         pipeline_layout: vk::PipelineLayout,
-        descriptor: Descriptor // probably should be arc or something but cloning is fine for now
+        descriptor: Descriptor, // probably should be arc or something but cloning is fine for now
+        /// one per dynamic binding, in binding order
+        dynamic_offsets: Vec<u32>
     },
     BindVertexBuffer {
         vertex_buffer: vk::Buffer
@@ -42,15 +105,34 @@ pub enum VustCommand {
     DrawIndexed {
         index_count: u32
     },
+    BeginRenderTarget {
+        renderpass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        extent: vk::Extent2D,
+        clear_values: Vec<vk::ClearValue>
+    },
+    EndRenderTarget,
+    ExecuteRenderGraph {
+        render_graph: CompiledRenderGraph
+    },
     UpdateDescriptorSet {
         descriptor: Descriptor,
         write_descriptor_infos: Vec<WriteDescriptorInfo>
     },
+    /// writes one binding of every frame's descriptor set, the recording frame's right away and the others at the start of their frame
+    UpdateDescriptorBinding {
+        descriptor: Descriptor,
+        binding: u32,
+        write_descriptor_info: WriteDescriptorInfo
+    },
     RenderSurface
 }
 
 unsafe impl Send for VustCommand {}
 
+/// gets the buffer's bytes once the frame is done, converts them to whatever the user asked for
+pub type ReadCallback = Box<dyn FnOnce(&[u8]) + Send>;
+
 pub(crate) struct DestroyBuffer {
     pub(crate) buffer: vk::Buffer,
     pub(crate) allocation: Allocation
@@ -59,6 +141,28 @@ pub(crate) struct DestroyBuffer {
 pub(crate) struct DestroyTexture {
     pub(crate) image: vk::Image,
     pub(crate) view: vk::ImageView,
-    pub(crate) sampler: vk::Sampler,
     pub(crate) allocation: Allocation
-}
\ No newline at end of file
+}
+
+pub(crate) struct DestroyRenderTarget {
+    pub(crate) renderpass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer
+}
+
+/// Staged texture upload waiting for the next frame's command buffer
+pub(crate) struct TextureUpdate {
+    pub(crate) staging_buffer: vk::Buffer,
+    pub(crate) image: vk::Image,
+    pub(crate) layer: u32,
+    pub(crate) offset: vk::Offset3D,
+    pub(crate) extent: vk::Extent3D
+}
+
+/// Readback buffer waiting for its frame's fence
+pub(crate) struct PendingRead {
+    pub(crate) frame: usize,
+    pub(crate) buffer: vk::Buffer,
+    pub(crate) allocation: Allocation,
+    pub(crate) size: vk::DeviceSize,
+    pub(crate) callback: ReadCallback
+}
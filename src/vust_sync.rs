@@ -1,4 +1,5 @@
 use std::sync::mpsc;
+use crate::error::VustError;
 
 /// Blocks the current thread until vust has finished rendering the previous frame
 /// 
@@ -7,13 +8,16 @@ use std::sync::mpsc;
 /// 
 /// Always run VustSyncer.sync() right after Vust.reset_command_buffer()
 pub struct VustSyncer {
-    pub(super) allow_messages_recv: mpsc::Receiver<()>
+    pub(super) allow_messages_recv: mpsc::Receiver<Result<bool, VustError>>
 }
 
 impl VustSyncer {
     /// Always run right after Vust.reset_command_buffer()
-    pub fn sync(&self) {
+    /// 
+    /// Returns false if the frame is skipped because the window has no size (minimized), draws until Vust.render_surface() are ignored.
+    /// Returns the error that killed the render thread, if it died
+    pub fn sync(&self) -> Result<bool, VustError> {
         // will block current thread
-        self.allow_messages_recv.recv().unwrap();
+        self.allow_messages_recv.recv().map_err(|_| VustError::RenderThreadDead)?
     }
 }
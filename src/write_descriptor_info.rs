@@ -1,4 +1,6 @@
 use ash::vk;
+use bytemuck::Pod;
+use crate::{buffer::TypedBuffer, render_target::RenderTarget, sampler::Sampler, texture::Texture};
 
 #[derive(Debug, Clone, Copy)]
 pub enum WriteDescriptorInfo {
@@ -14,6 +16,14 @@ pub enum WriteDescriptorInfo {
 }
 
 impl WriteDescriptorInfo {
+    /// Any texture with any sampler, not just the one it was built with
+    pub fn image(texture: &Texture, sampler: &Sampler) -> Self {
+        WriteDescriptorInfo::Image {
+            image_view: texture.view(),
+            sampler: sampler.handle()
+        }
+    }
+
     pub fn to_vk(&self) -> (Option<vk::DescriptorBufferInfo>, Option<vk::DescriptorImageInfo>) {
         match self {
             WriteDescriptorInfo::Buffer { buffer, offset, range } => (
@@ -39,3 +49,22 @@ impl WriteDescriptorInfo {
         }
     }
 }
+
+/// Whole written part of the buffer, or the whole buffer if nothing is written yet
+impl<T: Pod> From<&TypedBuffer<T>> for WriteDescriptorInfo {
+    fn from(buffer: &TypedBuffer<T>) -> Self {
+        WriteDescriptorInfo::Buffer {
+            buffer: buffer.handle(),
+            offset: 0,
+            // a range of 0 isnt valid
+            range: if buffer.is_empty() { vk::WHOLE_SIZE } else { buffer.size_in_bytes() }
+        }
+    }
+}
+
+/// Color image with the target's own sampler
+impl From<&RenderTarget> for WriteDescriptorInfo {
+    fn from(render_target: &RenderTarget) -> Self {
+        render_target.write_descriptor_info()
+    }
+}
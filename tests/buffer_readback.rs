@@ -0,0 +1,61 @@
+//! Reads buffers back from the gpu
+
+use ash::vk;
+use vust::{buffer::Buffer, error::VustError, Vust};
+
+mod common;
+
+#[test]
+fn buffer_readback() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Buffer Readback Test", 64, 64);
+
+    let data = (0..256u32).collect::<Vec<_>>();
+
+    let device_local_buffer = Buffer::builder()
+        .with_name("Device Local Buffer")
+        .with_usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&data)
+        .build(&vust, true).unwrap();
+    assert_eq!(device_local_buffer.read::<u32>().unwrap(), data);
+    // offset * size_of::<u32>() would wrap around
+    assert!(matches!(device_local_buffer.overwrite_offset(&data, usize::MAX / 2), Err(VustError::BufferOverflow { .. })));
+
+    let readback_buffer = Buffer::builder()
+        .with_name("Readback Buffer")
+        .with_usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED)
+        .with_data(&data)
+        .build(&vust, true).unwrap();
+    assert_eq!(readback_buffer.read::<u32>().unwrap(), data);
+
+    // upload buffers are read from their mapped memory, copying them back needs TRANSFER_SRC asked for
+    let upload_buffer = Buffer::builder()
+        .with_name("Upload Buffer")
+        .with_usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+        .with_data(&data)
+        .build(&vust, true).unwrap();
+    assert_eq!(upload_buffer.read::<u32>().unwrap(), data);
+    assert!(matches!(upload_buffer.read_async::<u32>(), Err(VustError::BufferNotReadable)));
+    drop(upload_buffer);
+
+    vust.reset_command_buffer().unwrap();
+    vust_syncer.sync().unwrap();
+    let device_local_read = device_local_buffer.read_async::<u32>().unwrap();
+    // the blocking read doesnt wait for the frame thats being recorded
+    assert_eq!(device_local_buffer.read::<u32>().unwrap(), data);
+    vust.render_surface().unwrap();
+
+    // shows up once the frame's fence is waited on again
+    for _ in 0..Vust::DEFAULT_FRAMES_IN_FLIGHT {
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.render_surface().unwrap();
+    }
+    assert_eq!(device_local_read.recv().unwrap(), data);
+
+    drop(device_local_buffer);
+    drop(readback_buffer);
+    vust.wait_idle().unwrap();
+}
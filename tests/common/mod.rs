@@ -0,0 +1,24 @@
+// not every test uses every helper
+#![allow(dead_code)]
+
+use ash::vk;
+use vust::{create_info::{PhysicalDeviceType, SurfaceCreateInfo, VustCreateInfo}, vust_sync::VustSyncer, Vust};
+
+/// Renders into offscreen images so the tests run without a window (lavapipe works too)
+pub fn headless_create_info(app_name: &str, width: u32, height: u32) -> VustCreateInfo {
+    VustCreateInfo::default()
+        .with_app_name(app_name)
+        .with_app_version(vust::make_api_version(0, 0, 1, 0))
+        .with_choose_physical_device(|physical_device| !matches!(physical_device.device_type, PhysicalDeviceType::NotSupported))
+        .with_surface_create_info(
+            SurfaceCreateInfo::Headless {
+                width,
+                height,
+                format: vk::Format::R8G8B8A8_UNORM
+            }
+        )
+}
+
+pub fn headless_vust(app_name: &str, width: u32, height: u32) -> (Vust, VustSyncer) {
+    Vust::new(headless_create_info(app_name, width, height)).unwrap()
+}
@@ -0,0 +1,220 @@
+//! Stencil mask drawn with a dynamic reference, then a draw that only lands outside of it without writing depth. A render target in between ends the main pass
+
+use std::mem::size_of;
+
+use ash::vk;
+use vust::{buffer::{Buffer, TypedBuffer}, error::VustError, pipeline::{DepthStencilState, GraphicsPipeline, GraphicsPipelineCreateInfo}, render_target::RenderTarget, Vust};
+
+mod common;
+
+fn triangle_pipeline_create_info(depth_stencil: DepthStencilState) -> GraphicsPipelineCreateInfo {
+    GraphicsPipelineCreateInfo {
+        name: "depth stencil triangle pipeline".to_string(),
+        vertex_bin: include_bytes!("triangle_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("triangle_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 5) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: 128.0,
+            height: 128.0,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width: 128,
+            height: 128
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: None,
+        depth_stencil,
+        multisample: Default::default()
+    }
+}
+
+fn stencil_op_state(compare_op: vk::CompareOp, pass_op: vk::StencilOp, reference: u32) -> vk::StencilOpState {
+    vk::StencilOpState {
+        fail_op: vk::StencilOp::KEEP,
+        pass_op,
+        depth_fail_op: vk::StencilOp::KEEP,
+        compare_op,
+        compare_mask: 0xFF,
+        write_mask: 0xFF,
+        reference
+    }
+}
+
+/// Green triangle in the middle and a red one covering the whole screen, both at depth 0
+fn triangle_buffers(vust: &Vust) -> (TypedBuffer<[f32; 5]>, TypedBuffer<[f32; 5]>) {
+    let center_buffer = Buffer::builder()
+        .with_name("Center Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 0.0, 1.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 1.0, 0.0]
+        ])
+        .build_typed(vust).unwrap();
+    let fullscreen_buffer = Buffer::builder()
+        .with_name("Fullscreen Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-1.0f32, -1.0, 1.0, 0.0, 0.0],
+            [3.0, -1.0, 1.0, 0.0, 0.0],
+            [-1.0, 3.0, 1.0, 0.0, 0.0]
+        ])
+        .build_typed(vust).unwrap();
+
+    (center_buffer, fullscreen_buffer)
+}
+
+#[test]
+fn depth_stencil() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Depth Stencil Test", 128, 128);
+
+    // writes 1 wherever it draws, the reference comes from set_stencil_reference()
+    let replace = stencil_op_state(vk::CompareOp::ALWAYS, vk::StencilOp::REPLACE, 0);
+    let mask_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(DepthStencilState {
+        depth_test: true,
+        stencil: Some((replace, replace)),
+        dynamic_stencil_reference: true,
+        ..Default::default()
+    })).unwrap();
+
+    let outside_mask = stencil_op_state(vk::CompareOp::NOT_EQUAL, vk::StencilOp::KEEP, 1);
+    let masked_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(DepthStencilState {
+        depth_test: true,
+        depth_write: false,
+        depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+        stencil: Some((outside_mask, outside_mask)),
+        ..Default::default()
+    })).unwrap();
+
+    // needs the depthBounds feature, which not every gpu has
+    let depth_bounds_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(DepthStencilState {
+        depth_test: true,
+        depth_bounds: Some((0.0, 0.5)),
+        ..Default::default()
+    }));
+    assert!(matches!(depth_bounds_pipeline, Ok(_) | Err(VustError::MissingFeature("depthBounds"))));
+
+    let (mask_buffer, fullscreen_buffer) = triangle_buffers(&vust);
+    let render_target = RenderTarget::builder()
+        .with_name("Empty Render Target")
+        .with_dimensions((32, 32))
+        .build(&vust)
+        .unwrap();
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..4 {
+        if frame == 3 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        vust.bind_pipeline(mask_pipeline.handle()).unwrap();
+        vust.set_stencil_reference(vk::StencilFaceFlags::FRONT_AND_BACK, 1).unwrap();
+        vust.draw_buffer(&mask_buffer).unwrap();
+
+        // the mask has to survive the main pass being ended and resumed
+        vust.begin_render_target(&render_target).unwrap();
+        vust.end_render_target().unwrap();
+
+        vust.bind_pipeline(masked_pipeline.handle()).unwrap();
+        vust.draw_buffer(&fullscreen_buffer).unwrap();
+
+        vust.render_surface().unwrap();
+    }
+
+    drop(mask_buffer);
+    drop(fullscreen_buffer);
+    drop(render_target);
+    vust.wait_idle().unwrap();
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    // outside the mask the fullscreen triangle is drawn, inside the mask stays
+    assert_eq!(&captured_image.pixels[0..4], &[255, 0, 0, 255]);
+    let center = (48 * 128 + 64) * 4;
+    assert_eq!(&captured_image.pixels[center..center + 4], &[0, 255, 0, 255]);
+}
+
+#[test]
+fn depth_after_render_target() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Depth After Render Target Test", 128, 128);
+
+    // LESS, the second triangle at the same depth fails wherever the first one was drawn
+    let depth_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(DepthStencilState {
+        depth_test: true,
+        ..Default::default()
+    })).unwrap();
+    let render_target = RenderTarget::builder()
+        .with_name("Empty Render Target")
+        .with_dimensions((32, 32))
+        .build(&vust)
+        .unwrap();
+    let (center_buffer, fullscreen_buffer) = triangle_buffers(&vust);
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..4 {
+        if frame == 3 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        vust.bind_pipeline(depth_pipeline.handle()).unwrap();
+        vust.draw_buffer(&center_buffer).unwrap();
+
+        // ends the main pass, it continues with the load renderpass afterwards
+        vust.begin_render_target(&render_target).unwrap();
+        vust.end_render_target().unwrap();
+
+        vust.bind_pipeline(depth_pipeline.handle()).unwrap();
+        vust.draw_buffer(&fullscreen_buffer).unwrap();
+
+        vust.render_surface().unwrap();
+    }
+
+    drop(center_buffer);
+    drop(fullscreen_buffer);
+    drop(render_target);
+    vust.wait_idle().unwrap();
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!(&captured_image.pixels[0..4], &[255, 0, 0, 255]);
+    let center = (48 * 128 + 64) * 4;
+    assert_eq!(&captured_image.pixels[center..center + 4], &[0, 255, 0, 255]);
+}
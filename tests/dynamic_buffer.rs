@@ -0,0 +1,194 @@
+//! Dynamic buffer growing past its capacity
+
+use std::{mem::size_of, sync::mpsc};
+
+use ash::vk;
+use vust::{buffer::Buffer, error::VustError, pipeline::{DescriptorSetBinding, DescriptorSetLayout, GraphicsPipeline, GraphicsPipelineCreateInfo}, render_graph::{GraphPass, RenderGraph}, texture::Texture, write_descriptor_info::WriteDescriptorInfo};
+
+mod common;
+
+#[test]
+fn dynamic_buffer() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Dynamic Buffer Test", 64, 64);
+
+    let mut dynamic_buffer = Buffer::builder::<u32>()
+        .with_name("Dynamic Buffer")
+        .with_capacity(4)
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+        .build_dynamic(&vust).unwrap();
+    assert_eq!(dynamic_buffer.capacity(), 4);
+
+    for i in 0..100 {
+        dynamic_buffer.push(i).unwrap();
+    }
+    assert_eq!(dynamic_buffer.len(), 100);
+    assert_eq!(dynamic_buffer.capacity(), 128);
+    assert_eq!(&dynamic_buffer.buffer().buffer().read::<u32>().unwrap()[..100], &(0..100).collect::<Vec<_>>());
+
+    // staged writes fail on the render thread, the cpu copy has to stay what the gpu buffer has
+    let mut staged_buffer = Buffer::builder::<u32>()
+        .with_name("Staged Dynamic Buffer")
+        .with_capacity(4)
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .build_dynamic(&vust).unwrap();
+    staged_buffer.extend_from_slice(&[1, 2]).unwrap();
+    let (staged_sender, staged_receiver) = mpsc::channel();
+    let mut staged_graph = RenderGraph::new();
+    staged_graph.add_pass(GraphPass::new("staged").with_record(move |_| {
+        let grow = staged_buffer.extend_from_slice(&[0; 8]);
+        let write = staged_buffer.write(&[0; 3]);
+        let _ = staged_sender.send((grow, write, staged_buffer.len(), staged_buffer.capacity()));
+    }));
+    let staged_graph = staged_graph.compile(&vust).unwrap();
+
+    // old buffers are retired over the next few frames
+    for frame in 0..4 {
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        if frame == 0 {
+            vust.execute_render_graph(&staged_graph).unwrap();
+        }
+        vust.render_surface().unwrap();
+    }
+
+    let (grow, write, len, capacity) = staged_receiver.recv().unwrap();
+    assert!(matches!(grow, Err(VustError::OnRenderThread)));
+    assert!(matches!(write, Err(VustError::OnRenderThread)));
+    assert_eq!((len, capacity), (2, 4));
+
+    dynamic_buffer.clear().unwrap();
+    assert!(dynamic_buffer.is_empty());
+    assert_eq!(dynamic_buffer.capacity(), 128);
+
+    drop(dynamic_buffer);
+    drop(staged_graph);
+    vust.wait_idle().unwrap();
+}
+
+#[test]
+fn dynamic_buffer_tracked_descriptor() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Dynamic Buffer Descriptor Test", 64, 64);
+
+    let pipeline = GraphicsPipeline::new(&vust, GraphicsPipelineCreateInfo {
+        name: "dynamic buffer descriptor pipeline".to_string(),
+        vertex_bin: include_bytes!("model_rect_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("model_rect_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 4) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_STRIP,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: 64.0,
+            height: 64.0,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: Some(DescriptorSetLayout {
+            bindings: vec![
+                DescriptorSetBinding {
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT
+                },
+                DescriptorSetBinding {
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    stage_flags: vk::ShaderStageFlags::VERTEX
+                }
+            ]
+        }),
+        depth_stencil: Default::default(),
+        multisample: Default::default()
+    }).unwrap();
+    let descriptor = pipeline.create_descriptor(&vust).unwrap();
+
+    let rect_buffer = Buffer::builder()
+        .with_name("Rect Vertex Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+        .with_data(&[
+            [-0.5f32, -0.5, 0.0, 0.0],
+            [-0.5, 0.5, 0.0, 1.0],
+            [0.5, -0.5, 1.0, 0.0],
+            [0.5, 0.5, 1.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+
+    let white_texture = Texture::builder()
+        .with_name("White Texture")
+        .with_data(&[255, 255, 255, 255])
+        .with_dimensions((1, 1))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .build(&vust)
+        .unwrap();
+    vust.update_descriptor_binding(&descriptor, 0, WriteDescriptorInfo::Image { image_view: white_texture.view(), sampler: white_texture.sampler() }).unwrap();
+
+    // column major, the first model pushes the rect off screen
+    let model = |x: f32| [1.0f32, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, x, 0.0, 0.0, 1.0];
+    let mut model_buffer = Buffer::builder()
+        .with_name("Model Dynamic Buffer")
+        .with_data(&[model(10.0)])
+        .with_usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+        .build_dynamic(&vust).unwrap();
+    model_buffer.track_descriptor(&descriptor, 1).unwrap();
+
+    // the frames before grow nothing, the captured one grows right before its draw
+    let mut capture = None;
+    for frame in 0..3 {
+        if frame == 2 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        if frame == 2 {
+            model_buffer.write(&[model(0.0), model(0.0)]).unwrap();
+            assert_eq!(model_buffer.capacity(), 2);
+        }
+
+        vust.bind_pipeline(pipeline.handle()).unwrap();
+        vust.bind_descriptor_set(pipeline.pipeline_layout(), &descriptor).unwrap();
+        vust.bind_vertex_buffer(rect_buffer.handle()).unwrap();
+        vust.draw(4).unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    let center = (32 * 64 + 32) * 4;
+    assert_eq!(&captured_image.pixels[center..center + 4], &[255, 255, 255, 255]);
+
+    drop(model_buffer);
+    drop(white_texture);
+    drop(rect_buffer);
+    vust.wait_idle().unwrap();
+}
@@ -0,0 +1,118 @@
+//! Triangle rendered into offscreen images, no window needed (runs on lavapipe)
+
+use std::mem::size_of;
+
+use ash::vk;
+use vust::{buffer::Buffer, create_info::{PhysicalDeviceType, VustCreateInfo}, error::VustError, pipeline::GraphicsPipeline, Vust};
+
+#[test]
+fn headless_triangle() {
+    let vust_create_info = VustCreateInfo::default()
+        .with_app_name("Vust Headless Triangle Test")
+        .with_app_version(vust::make_api_version(0, 0, 1, 0))
+        .with_choose_physical_device(|physical_device| !matches!(physical_device.device_type, PhysicalDeviceType::NotSupported))
+        .with_frames_in_flight(3)
+        .with_surface_create_info(
+            vust::create_info::SurfaceCreateInfo::Headless {
+                width: 800,
+                height: 600,
+                format: vk::Format::R8G8B8A8_SRGB
+            }
+        );
+
+    let (mut vust, vust_syncer) = Vust::new(vust_create_info).unwrap();
+
+    let pipeline = GraphicsPipeline::new(
+        &vust,
+        vust::pipeline::GraphicsPipelineCreateInfo {
+            name: "headless triangle pipeline".to_string(),
+            vertex_bin: include_bytes!("triangle_shaders/default.vert.spv").to_vec(),
+            fragment_bin: include_bytes!("triangle_shaders/default.frag.spv").to_vec(),
+            vertex_binding_descriptions: vec![
+                vk::VertexInputBindingDescription::builder()
+                    .binding(0)
+                    .stride((size_of::<f32>() * 5) as u32)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+                    .build()
+            ],
+            vertex_attribute_descriptions: vec![
+                vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(0)
+                    .offset(0)
+                    .format(vk::Format::R32G32_SFLOAT)
+                    .build(),
+                vk::VertexInputAttributeDescription::builder()
+                    .binding(0)
+                    .location(1)
+                    .offset(8)
+                    .format(vk::Format::R32G32B32_SFLOAT)
+                    .build()
+            ],
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            viewport: vust::pipeline::Viewport::Static {
+                x: 0.0,
+                y: 0.0,
+                width: 800.0,
+                height: 600.0,
+                min_depth: 0.0,
+                max_depth: 1.0
+            },
+            scissor: vust::pipeline::Scissor::Static {
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 600
+            },
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vust::pipeline::CullMode::None,
+            descriptor_set_layout: None,
+            depth_stencil: Default::default(),
+            multisample: Default::default()
+        }
+    ).unwrap();
+
+    let mut triangle_buffer = Buffer::builder()
+        .with_name("Triangle Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        // filled through a staging buffer
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 1.0, 0.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+    assert_eq!(triangle_buffer.len(), 3);
+    // offset + len would wrap around
+    assert!(matches!(triangle_buffer.write_offset(&[[0.0; 5]], usize::MAX), Err(VustError::BufferOverflow { .. })));
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..10 {
+        if frame == 9 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.bind_pipeline(pipeline.handle()).unwrap();
+        vust.draw_buffer(&triangle_buffer).unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    // drop before the render thread shuts down
+    drop(triangle_buffer);
+    vust.wait_idle().unwrap();
+
+    // arrives once the last frame is done
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!((captured_image.extent.width, captured_image.extent.height), (800, 600));
+    assert_eq!(captured_image.pixels.len(), 800 * 600 * 4);
+
+    // corner is outside the triangle, middle is inside it
+    assert_eq!(&captured_image.pixels[0..4], &[0, 0, 255, 255]);
+    let center = (300 * 800 + 400) * 4;
+    assert_ne!(&captured_image.pixels[center..center + 4], &[0, 0, 255, 255]);
+}
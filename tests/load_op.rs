@@ -0,0 +1,35 @@
+//! LoadOp::Load keeps what earlier frames left in the images instead of clearing them
+
+use vust::create_info::LoadOp;
+
+mod common;
+
+#[test]
+fn load_op() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Load Op Test", 64, 64);
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..8 {
+        // every image has been cleared blue by then, red should never show up
+        if frame == 4 {
+            vust.set_load_op(LoadOp::Load).unwrap();
+            vust.set_clear_color([1.0, 0.0, 0.0, 1.0]).unwrap();
+        }
+        if frame == 7 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    vust.wait_idle().unwrap();
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!(&captured_image.pixels[0..4], &[0, 0, 255, 255]);
+    let center = (32 * 64 + 32) * 4;
+    assert_eq!(&captured_image.pixels[center..center + 4], &[0, 0, 255, 255]);
+}
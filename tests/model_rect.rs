@@ -1,4 +1,4 @@
-/// Rect with texture and index buffer
+//! Rect with texture and index buffer
 
 use std::{io::Cursor, mem::size_of, ptr::null};
 use ash::vk;
@@ -27,7 +27,7 @@ fn model_rect() {
         )
         .with_framebuffer_size((window.get_framebuffer_size().0 as usize, window.get_framebuffer_size().1 as usize));
 
-    let (mut vust, vust_syncer) = Vust::new(vust_create_info);
+    let (mut vust, vust_syncer) = Vust::new(vust_create_info).unwrap();
 
     let pipeline = GraphicsPipeline::new(
         &vust,
@@ -75,9 +75,10 @@ fn model_rect() {
                     ]
                 }
             ),
-            enable_depth_test: false
+            depth_stencil: Default::default(),
+            multisample: Default::default()
         }
-    );
+    ).unwrap();
 
     let descriptor = pipeline.create_descriptor(&mut vust).unwrap();
 
@@ -91,14 +92,14 @@ fn model_rect() {
             0.5, 0.5, 1.0, 1.0, // top right
             0.5, -0.5, 1.0, 0.0 // bottom right
         ])
-        .build(&mut vust, true);
+        .build(&mut vust, true).unwrap();
 
     let mut index_buffer = Buffer::builder()
         .with_name("Rect Index Buffer")
         .with_usage(vk::BufferUsageFlags::INDEX_BUFFER)
         .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
         .with_data(&[0, 1, 2, 0, 2, 3])
-        .build(&mut vust, true);
+        .build(&mut vust, true).unwrap();
 
     let image = image::load(Cursor::new(include_bytes!("textures/green amogus.png")), image::ImageFormat::Png).unwrap();
     let mut texture = Texture::builder()
@@ -116,23 +117,23 @@ fn model_rect() {
             .with_data(model.as_slice())
             .with_usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
             .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
-            .build(&mut vust, true); 
+            .build(&mut vust, true).unwrap(); 
 
     while !window.should_close() {
         glfw.poll_events();
 
-        vust.reset_command_buffer();
-        vust_syncer.sync();
-        vust.bind_pipeline(pipeline.handle());
-        vust.bind_viewport(vk::Viewport { x: 0.0, y: 0.0, width: 800.0, height: 600.0, min_depth: 0.0, max_depth: 1.0 });
-        vust.bind_scissor(vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: vk::Extent2D { width: 800, height: 600 } });
-        vust.update_descriptor_set(&descriptor, vec![WriteDescriptorInfo::Image { image_view: texture.view(), sampler: texture.sampler() }, WriteDescriptorInfo::Buffer { buffer: model_uniform_buffer.handle(), offset: 0, range: size_of::<glm::Mat4>() as u64 }]);
-        vust.bind_descriptor_set(pipeline.pipeline_layout(), &descriptor);
-        vust.bind_vertex_buffer(rect_buffer.handle());
-        vust.bind_index_buffer(index_buffer.handle());
-        vust.draw_indexed(6);
-        vust.render_surface();
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.bind_pipeline(pipeline.handle()).unwrap();
+        vust.bind_viewport(vk::Viewport { x: 0.0, y: 0.0, width: 800.0, height: 600.0, min_depth: 0.0, max_depth: 1.0 }).unwrap();
+        vust.bind_scissor(vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: vk::Extent2D { width: 800, height: 600 } }).unwrap();
+        vust.update_descriptor_set(&descriptor, vec![WriteDescriptorInfo::Image { image_view: texture.view(), sampler: texture.sampler() }, WriteDescriptorInfo::Buffer { buffer: model_uniform_buffer.handle(), offset: 0, range: size_of::<glm::Mat4>() as u64 }]).unwrap();
+        vust.bind_descriptor_set(pipeline.pipeline_layout(), &descriptor).unwrap();
+        vust.bind_vertex_buffer(rect_buffer.handle()).unwrap();
+        vust.bind_index_buffer(index_buffer.handle()).unwrap();
+        vust.draw_indexed(6).unwrap();
+        vust.render_surface().unwrap();
     }
 
-    vust.wait_idle(); 
+    vust.wait_idle().unwrap();
 }
\ No newline at end of file
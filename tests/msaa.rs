@@ -0,0 +1,110 @@
+//! Triangle drawn with 4x msaa resolved onto the offscreen images
+
+use std::mem::size_of;
+
+use ash::vk;
+use vust::{buffer::Buffer, pipeline::{GraphicsPipeline, GraphicsPipelineCreateInfo, MultisampleState}, Vust};
+
+mod common;
+
+fn triangle_pipeline_create_info(width: u32, height: u32, multisample: MultisampleState) -> GraphicsPipelineCreateInfo {
+    GraphicsPipelineCreateInfo {
+        name: "msaa triangle pipeline".to_string(),
+        vertex_bin: include_bytes!("triangle_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("triangle_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 5) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width,
+            height
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: None,
+        depth_stencil: vust::pipeline::DepthStencilState { depth_test: true, ..Default::default() },
+        multisample
+    }
+}
+
+#[test]
+fn msaa() {
+    let (mut vust, vust_syncer) = Vust::new(common::headless_create_info("Vust MSAA Test", 96, 96).with_msaa(4)).unwrap();
+    // 4x is required for color and depth by the spec
+    assert_eq!(vust.samples(), vk::SampleCountFlags::TYPE_4);
+
+    let pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(128, 128, MultisampleState::default())).unwrap();
+    let alpha_to_coverage_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(128, 128, MultisampleState {
+        alpha_to_coverage: true,
+        min_sample_shading: Some(1.0)
+    })).unwrap();
+
+    let triangle_buffer = Buffer::builder()
+        .with_name("Triangle Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 1.0, 0.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..6 {
+        // msaa color image has to be recreated with the rest
+        if frame == 2 {
+            vust.resize(128, 128).unwrap();
+        }
+        if frame == 5 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        vust.bind_pipeline(if frame % 2 == 0 { pipeline.handle() } else { alpha_to_coverage_pipeline.handle() }).unwrap();
+        vust.draw_buffer(&triangle_buffer).unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    drop(triangle_buffer);
+    vust.wait_idle().unwrap();
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!((captured_image.extent.width, captured_image.extent.height), (128, 128));
+    assert_eq!(&captured_image.pixels[0..4], &[0, 0, 255, 255]);
+    let center = (48 * 128 + 64) * 4;
+    assert_ne!(&captured_image.pixels[center..center + 4], &[0, 0, 255, 255]);
+}
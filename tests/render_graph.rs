@@ -0,0 +1,219 @@
+//! Passes added out of order to a render graph, two transients sharing one render target
+
+use std::{mem::size_of, sync::mpsc};
+
+use ash::vk;
+use vust::{buffer::Buffer, error::VustError, pipeline::{GraphicsPipeline, GraphicsPipelineCreateInfo}, render_graph::{GraphPass, GraphResource, RenderGraph, TransientTarget}, render_target::RenderTarget};
+
+mod common;
+
+fn triangle_pipeline_create_info(width: u32, height: u32) -> GraphicsPipelineCreateInfo {
+    GraphicsPipelineCreateInfo {
+        name: "render graph triangle pipeline".to_string(),
+        vertex_bin: include_bytes!("triangle_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("triangle_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 5) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width,
+            height
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: None,
+        depth_stencil: vust::pipeline::DepthStencilState { depth_test: true, ..Default::default() },
+        multisample: Default::default()
+    }
+}
+
+#[test]
+fn render_graph() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Render Graph Test", 128, 128);
+
+    let triangle_buffer = Buffer::builder()
+        .with_name("Triangle Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 1.0, 0.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+    let vertex_buffer = triangle_buffer.handle();
+    // same spot, drawn before the graph so its depth makes the graph's surface pass fail there
+    let blocker_buffer = Buffer::builder()
+        .with_name("Blocker Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 0.0, 1.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 1.0, 0.0]
+        ])
+        .build_typed(&vust).unwrap();
+
+    let render_target = RenderTarget::builder()
+        .with_name("Graph Render Target")
+        .with_dimensions((64, 64))
+        .build(&vust)
+        .unwrap();
+    let target_pipeline = GraphicsPipeline::new_for_render_target(&vust, &render_target, triangle_pipeline_create_info(64, 64)).unwrap();
+    let surface_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(128, 128)).unwrap();
+
+    let transient_target = TransientTarget {
+        dimensions: (32, 32),
+        color_format: vk::Format::R8G8B8A8_UNORM,
+        depth_format: None
+    };
+
+    // added backwards, the graph has to sort them
+    let mut render_graph = RenderGraph::new();
+    let first = render_graph.add_transient(transient_target);
+    let second = render_graph.add_transient(transient_target);
+
+    let surface_pipeline_handle = surface_pipeline.handle();
+    render_graph.add_pass(GraphPass::new("surface")
+        .with_read(&triangle_buffer)
+        .with_record(move |pass| {
+            pass.bind_pipeline(surface_pipeline_handle);
+            pass.bind_vertex_buffer(vertex_buffer);
+            pass.draw(3);
+        }));
+    render_graph.add_pass(GraphPass::new("second")
+        .with_transient_target(second)
+        .with_read(&render_target)
+        .with_read(&triangle_buffer));
+    let target_pipeline_handle = target_pipeline.handle();
+    render_graph.add_pass(GraphPass::new("use first")
+        .with_render_target(&render_target)
+        .with_read(first)
+        .with_read(&triangle_buffer)
+        .with_record(move |pass| {
+            pass.bind_pipeline(target_pipeline_handle);
+            pass.bind_vertex_buffer(vertex_buffer);
+            pass.draw(3);
+        }));
+    render_graph.add_pass(GraphPass::new("first")
+        .with_transient_target(first)
+        .with_read(&triangle_buffer));
+
+    let render_graph = render_graph.compile(&vust).unwrap();
+    assert_eq!(render_graph.pass_order(), vec!["first", "use first", "second", "surface"]);
+    // first is done before second starts
+    assert_eq!(render_graph.transient_render_target_count(), 1);
+
+    let transient_render_target = render_graph.render_target(first).unwrap();
+    assert_eq!(transient_render_target.image(), render_graph.render_target(second).unwrap().image());
+    let transient_pipeline = GraphicsPipeline::new_for_render_target(&vust, transient_render_target, triangle_pipeline_create_info(32, 32)).unwrap();
+    for pass_name in ["first", "second"] {
+        let transient_pipeline_handle = transient_pipeline.handle();
+        assert!(render_graph.set_record(pass_name, move |pass| {
+            pass.bind_pipeline(transient_pipeline_handle);
+            pass.bind_vertex_buffer(vertex_buffer);
+            pass.draw(3);
+        }));
+    }
+    assert!(!render_graph.set_record("missing", |_| {}));
+
+    // uploading from a record function would wait for the render thread it runs on
+    let upload_buffer = Buffer::builder()
+        .with_name("Upload Buffer")
+        .with_usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[0u32; 4])
+        .build(&vust, true).unwrap();
+    let (upload_sender, upload_receiver) = mpsc::channel();
+    let mut upload_graph = RenderGraph::new();
+    upload_graph.add_pass(GraphPass::new("upload").with_record(move |_| {
+        let _ = upload_sender.send(upload_buffer.overwrite_all(&[1u32; 4]));
+    }));
+    let upload_graph = upload_graph.compile(&vust).unwrap();
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..4 {
+        if frame == 3 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        // the render target passes end the main pass, the surface pass has to resume it with the depth
+        vust.bind_pipeline(surface_pipeline.handle()).unwrap();
+        vust.draw_buffer(&blocker_buffer).unwrap();
+        vust.execute_render_graph(&render_graph).unwrap();
+        if frame == 0 {
+            vust.execute_render_graph(&upload_graph).unwrap();
+        }
+        vust.render_surface().unwrap();
+    }
+
+    vust.wait_idle().unwrap();
+    drop(render_graph);
+    drop(upload_graph);
+    drop(render_target);
+    drop(triangle_buffer);
+    drop(blocker_buffer);
+
+    assert!(matches!(upload_receiver.recv().unwrap(), Err(VustError::OnRenderThread)));
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!(&captured_image.pixels[0..4], &[0, 0, 255, 255]);
+    let center = (48 * 128 + 64) * 4;
+    assert_eq!(&captured_image.pixels[center..center + 4], &[0, 255, 0, 255]);
+
+    // each pass reads what the other one writes
+    let mut cyclic_graph = RenderGraph::new();
+    let a = cyclic_graph.add_transient(transient_target);
+    let b = cyclic_graph.add_transient(transient_target);
+    cyclic_graph.add_pass(GraphPass::new("a").with_transient_target(a).with_read(b));
+    cyclic_graph.add_pass(GraphPass::new("b").with_transient_target(b).with_read(a));
+    assert!(matches!(cyclic_graph.compile(&vust), Err(VustError::RenderGraphCycle(_))));
+
+    // both read and write the same buffer, thats not a cycle, they keep the order they were added in
+    let accumulate = GraphResource::Buffer(vk::Buffer::null());
+    let mut accumulate_graph = RenderGraph::new();
+    accumulate_graph.add_pass(GraphPass::new("accumulate a").with_read(accumulate).with_write(accumulate));
+    accumulate_graph.add_pass(GraphPass::new("accumulate b").with_read(accumulate).with_write(accumulate));
+    assert_eq!(accumulate_graph.compile(&vust).unwrap().pass_order(), vec!["accumulate a", "accumulate b"]);
+}
+
+#[test]
+#[should_panic(expected = "transient target")]
+fn render_graph_transient_target_not_transient() {
+    // would otherwise quietly draw into the surface
+    let _ = GraphPass::new("not transient").with_transient_target(GraphResource::Buffer(vk::Buffer::null()));
+}
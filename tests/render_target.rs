@@ -0,0 +1,120 @@
+//! Triangle drawn into an offscreen render target in the middle of a frame
+
+use std::mem::size_of;
+
+use ash::vk;
+use vust::{buffer::Buffer, pipeline::{GraphicsPipeline, GraphicsPipelineCreateInfo}, render_target::RenderTarget, write_descriptor_info::WriteDescriptorInfo};
+
+mod common;
+
+fn triangle_pipeline_create_info(width: u32, height: u32) -> GraphicsPipelineCreateInfo {
+    GraphicsPipelineCreateInfo {
+        name: "render target triangle pipeline".to_string(),
+        vertex_bin: include_bytes!("triangle_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("triangle_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 5) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width,
+            height
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: None,
+        depth_stencil: vust::pipeline::DepthStencilState { depth_test: true, ..Default::default() },
+        multisample: Default::default()
+    }
+}
+
+#[test]
+fn render_target() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Render Target Test", 128, 128);
+
+    let render_target = RenderTarget::builder()
+        .with_name("Minimap")
+        .with_dimensions((64, 32))
+        .with_color_format(vk::Format::R16G16B16A16_SFLOAT)
+        .with_depth_format(vk::Format::D32_SFLOAT)
+        .with_clear_color([1.0, 0.0, 0.0, 1.0])
+        .build(&vust)
+        .unwrap();
+    assert_eq!((render_target.extent().width, render_target.extent().height), (64, 32));
+    assert!(matches!(WriteDescriptorInfo::from(&render_target), WriteDescriptorInfo::Image { image_view, .. } if image_view == render_target.view()));
+
+    let surface_pipeline = GraphicsPipeline::new(&vust, triangle_pipeline_create_info(128, 128)).unwrap();
+    let target_pipeline = GraphicsPipeline::new_for_render_target(&vust, &render_target, triangle_pipeline_create_info(64, 32)).unwrap();
+
+    let triangle_buffer = Buffer::builder()
+        .with_name("Triangle Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        .with_data(&[
+            [-0.5f32, -0.5, 1.0, 0.0, 0.0],
+            [0.5, -0.5, 0.0, 1.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+
+    vust.set_clear_color([0.0, 0.0, 1.0, 1.0]).unwrap();
+
+    let mut capture = None;
+    for frame in 0..4 {
+        if frame == 3 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+
+        // surface, then the target, then the surface again without losing the first triangle
+        vust.bind_pipeline(surface_pipeline.handle()).unwrap();
+        vust.draw_buffer(&triangle_buffer).unwrap();
+
+        vust.begin_render_target(&render_target).unwrap();
+        vust.bind_pipeline(target_pipeline.handle()).unwrap();
+        vust.draw_buffer(&triangle_buffer).unwrap();
+        vust.end_render_target().unwrap();
+
+        vust.bind_pipeline(surface_pipeline.handle()).unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    drop(triangle_buffer);
+    drop(render_target);
+    vust.wait_idle().unwrap();
+
+    let captured_image = capture.unwrap().recv().unwrap();
+    assert_eq!(&captured_image.pixels[0..4], &[0, 0, 255, 255]);
+    let center = (48 * 128 + 64) * 4;
+    assert_ne!(&captured_image.pixels[center..center + 4], &[0, 0, 255, 255]);
+}
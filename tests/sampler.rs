@@ -0,0 +1,46 @@
+//! Sampler caching and custom texture samplers
+
+use ash::vk;
+use vust::{sampler::Sampler, texture::Texture, write_descriptor_info::WriteDescriptorInfo};
+
+mod common;
+
+#[test]
+fn sampler() {
+    let (vust, _vust_syncer) = common::headless_vust("Vust Sampler Test", 64, 64);
+
+    let clamped = Sampler::builder()
+        .with_filter(vk::Filter::LINEAR)
+        .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .with_anisotropy(1024.0);
+    let first = clamped.build(&vust).unwrap();
+    let second = clamped.build(&vust).unwrap();
+    assert_eq!(first, second);
+
+    let shadow = Sampler::builder()
+        .with_filter(vk::Filter::LINEAR)
+        .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+        .with_border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+        .with_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+        .build(&vust)
+        .unwrap();
+    assert_ne!(first, shadow);
+
+    let texture = Texture::builder()
+        .with_name("Clamped Texture")
+        .with_data(&[255; 4 * 4 * 4])
+        .with_dimensions((4, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_sampler(clamped)
+        .build(&vust)
+        .unwrap();
+    assert_eq!(texture.sampler(), first.handle());
+
+    let WriteDescriptorInfo::Image { image_view, sampler } = WriteDescriptorInfo::image(&texture, &shadow) else {
+        panic!("expected an image descriptor");
+    };
+    assert_eq!((image_view, sampler), (texture.view(), shadow.handle()));
+
+    drop(texture);
+    vust.wait_idle().unwrap();
+}
@@ -0,0 +1,128 @@
+//! Compressed formats and ktx2/dds loading, the files are built in memory so no assets are needed
+
+use ash::vk;
+use vust::{error::VustError, texture::Texture};
+
+mod common;
+
+/// 4x4 then 2x2, every level has `layers` layers
+fn ktx2(format: vk::Format, layers: u32, level_data: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    for value in [format.as_raw() as u32, 1, 4, 4, 0, layers, 1, level_data.len() as u32, 0] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.resize(80, 0);
+
+    // level index first, data right after it smallest level first like the spec wants
+    let mut offset = 80 + level_data.len() * 24;
+    let mut offsets = vec![0; level_data.len()];
+    for level in (0..level_data.len()).rev() {
+        offsets[level] = offset;
+        offset += level_data[level].len();
+    }
+    for (level, data) in level_data.iter().enumerate() {
+        for value in [offsets[level] as u64, data.len() as u64, data.len() as u64] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for data in level_data.iter().rev() {
+        bytes.extend_from_slice(data);
+    }
+
+    bytes
+}
+
+/// 8x8 with a full mip chain (8x8, 4x4, 2x2, 1x1)
+fn dxt1_dds() -> Vec<u8> {
+    let mut header = [0u32; 32];
+    header[0] = 0x20534444; // "DDS "
+    header[1] = 124;
+    header[2] = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000;
+    header[3] = 8;
+    header[4] = 8;
+    header[7] = 4;
+    header[19] = 32;
+    header[20] = 0x4;
+    header[21] = u32::from_le_bytes(*b"DXT1");
+
+    let mut bytes = header.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<_>>();
+    // 4 blocks then 1 for each smaller level
+    bytes.resize(bytes.len() + (4 + 1 + 1 + 1) * 8, 0x55);
+    bytes
+}
+
+#[test]
+fn texture_files() {
+    let (vust, _vust_syncer) = common::headless_vust("Vust Texture Files Test", 64, 64);
+
+    let ktx2_bytes = ktx2(vk::Format::R8G8B8A8_UNORM, 2, &[vec![255; 4 * 4 * 4 * 2], vec![128; 2 * 2 * 4 * 2]]);
+    let ktx2_texture = Texture::from_ktx2(&ktx2_bytes).unwrap()
+        .with_name("Ktx2 Texture")
+        .build(&vust)
+        .unwrap();
+    assert_eq!(ktx2_texture.dimensions(), (4, 4));
+    assert_eq!(ktx2_texture.mip_levels(), 2);
+    assert_eq!(ktx2_texture.layers(), 2);
+
+    // level 1 is one byte short
+    let broken_bytes = ktx2(vk::Format::R8G8B8A8_UNORM, 2, &[vec![255; 4 * 4 * 4 * 2], vec![128; 2 * 2 * 4 * 2 - 1]]);
+    assert!(matches!(Texture::from_ktx2(&broken_bytes), Err(VustError::InvalidTextureFile(_))));
+    assert!(matches!(Texture::from_dds(&ktx2_bytes), Err(VustError::InvalidTextureFile(_))));
+
+    // headers that would overflow or shift too far have to be rejected instead of panicking
+    let patched = |bytes: &[u8], offset: usize, value: &[u8]| {
+        let mut bytes = bytes.to_vec();
+        bytes[offset..offset + value.len()].copy_from_slice(value);
+        bytes
+    };
+    for broken_bytes in [
+        patched(&ktx2_bytes, 20, &0u32.to_le_bytes()), // width
+        patched(&patched(&ktx2_bytes, 32, &u32::MAX.to_le_bytes()), 36, &6u32.to_le_bytes()), // layers times faces
+        patched(&ktx2_bytes, 40, &40u32.to_le_bytes()), // levels
+        patched(&ktx2_bytes, 80, &u64::MAX.to_le_bytes()) // level 0 offset
+    ] {
+        assert!(matches!(Texture::from_ktx2(&broken_bytes), Err(VustError::InvalidTextureFile(_))));
+    }
+    for broken_bytes in [
+        patched(&dxt1_dds(), 16, &0u32.to_le_bytes()), // width
+        patched(&dxt1_dds(), 28, &40u32.to_le_bytes()), // mip map count
+        patched(&dxt1_dds(), 16, &u32::MAX.to_le_bytes()) // width, way more data than the file has
+    ] {
+        assert!(matches!(Texture::from_dds(&broken_bytes), Err(VustError::InvalidTextureFile(_))));
+    }
+
+    if vust.supports_texture_format(vk::Format::BC1_RGBA_UNORM_BLOCK) {
+        let dds_texture = Texture::from_dds(&dxt1_dds()).unwrap()
+            .with_name("Dds Texture")
+            .build(&vust)
+            .unwrap();
+        assert_eq!(dds_texture.mip_levels(), 4);
+
+        // regions have to line up with the 4x4 blocks
+        dds_texture.update_region((4, 0), (4, 4), &[0; 8]).unwrap();
+        let result = dds_texture.update_region((2, 0), (4, 4), &[0; 8]);
+        assert!(matches!(result, Err(VustError::TextureRegionOutOfBounds { .. })));
+    }
+
+    // one 4x4 block per 6x6 texel
+    if vust.supports_texture_format(vk::Format::ASTC_6X6_UNORM_BLOCK) {
+        let astc_texture = Texture::builder()
+            .with_name("Astc Texture")
+            .with_data(&[0; 3 * 2 * 16])
+            .with_dimensions((16, 8))
+            .with_format(vk::Format::ASTC_6X6_UNORM_BLOCK)
+            .build(&vust)
+            .unwrap();
+        drop(astc_texture);
+    } else {
+        let result = Texture::builder()
+            .with_data(&[0; 3 * 2 * 16])
+            .with_dimensions((16, 8))
+            .with_format(vk::Format::ASTC_6X6_UNORM_BLOCK)
+            .build(&vust);
+        assert!(matches!(result, Err(VustError::UnsupportedTextureFormat(_))));
+    }
+
+    drop(ktx2_texture);
+    vust.wait_idle().unwrap();
+}
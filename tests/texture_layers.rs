@@ -0,0 +1,79 @@
+//! Texture arrays and cubemaps
+
+use ash::vk;
+use vust::{error::VustError, texture::{MipGen, Texture, TextureKind}};
+
+mod common;
+
+#[test]
+fn texture_layers() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Texture Layers Test", 64, 64);
+
+    let sprite_sheet = Texture::builder()
+        .with_name("Sprite Sheet")
+        .with_data(&[255; 8 * 8 * 4 * 3])
+        .with_dimensions((8, 8))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_kind(TextureKind::Array2D)
+        .with_layers(3)
+        .with_mipmaps(MipGen::Auto)
+        .build(&vust)
+        .unwrap();
+    assert_eq!(sprite_sheet.layers(), 3);
+    assert_eq!(sprite_sheet.kind(), TextureKind::Array2D);
+
+    // every face has its own 4x4 and 2x2 level
+    let faces = (0..6u8).map(|face| vec![face * 40; (4 * 4 + 2 * 2) * 4]).collect::<Vec<_>>();
+    let skybox = Texture::builder()
+        .with_name("Skybox")
+        .with_layer_data(&faces.iter().map(|face| face.as_slice()).collect::<Vec<_>>())
+        .with_dimensions((4, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_kind(TextureKind::Cube)
+        .with_mipmaps(MipGen::Provided(2))
+        .build(&vust)
+        .unwrap();
+    assert_eq!(skybox.layers(), 6);
+    assert_eq!(skybox.mip_levels(), 2);
+
+    skybox.update_layer_region(5, (0, 0), (2, 2), &[0; 2 * 2 * 4]).unwrap();
+    let result = skybox.update_layer_region(6, (0, 0), (2, 2), &[0; 2 * 2 * 4]);
+    assert!(matches!(result, Err(VustError::TextureLayerOutOfBounds { layer: 6, layers: 6 })));
+
+    vust.reset_command_buffer().unwrap();
+    vust_syncer.sync().unwrap();
+    vust.render_surface().unwrap();
+
+    let result = Texture::builder()
+        .with_data(&[255; 8 * 4 * 4 * 6])
+        .with_dimensions((8, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_kind(TextureKind::Cube)
+        .build(&vust);
+    assert!(matches!(result, Err(VustError::InvalidTextureData)));
+
+    // 5 faces isnt a cube
+    let result = Texture::builder()
+        .with_layer_data(&faces[..5].iter().map(|face| face.as_slice()).collect::<Vec<_>>())
+        .with_dimensions((4, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_kind(TextureKind::Cube)
+        .with_mipmaps(MipGen::Provided(2))
+        .build(&vust);
+    assert!(matches!(result, Err(VustError::InvalidTextureData)));
+
+    // two cubes only work with the imageCubeArray feature
+    let result = Texture::builder()
+        .with_data(&[255; 4 * 4 * 4 * 12])
+        .with_dimensions((4, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_kind(TextureKind::Cube)
+        .with_layers(2)
+        .build(&vust);
+    assert!(matches!(result, Ok(_) | Err(VustError::MissingFeature("imageCubeArray"))));
+    drop(result);
+
+    drop(sprite_sheet);
+    drop(skybox);
+    vust.wait_idle().unwrap();
+}
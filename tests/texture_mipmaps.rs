@@ -0,0 +1,44 @@
+//! Mip chains generated on the gpu and provided by the user
+
+use ash::vk;
+use vust::{error::VustError, texture::{MipGen, Texture}};
+
+mod common;
+
+#[test]
+fn texture_mipmaps() {
+    let (vust, _vust_syncer) = common::headless_vust("Vust Texture Mipmaps Test", 64, 64);
+
+    let auto_texture = Texture::builder()
+        .with_name("Auto Mipmaps")
+        .with_data(&[255; 256 * 128 * 4])
+        .with_dimensions((256, 128))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_filter(vk::Filter::LINEAR)
+        .with_mipmaps(MipGen::Auto)
+        .build(&vust)
+        .unwrap();
+    assert_eq!(auto_texture.mip_levels(), 9);
+
+    // 4x4 then 2x2
+    let provided_texture = Texture::builder()
+        .with_name("Provided Mipmaps")
+        .with_data(&[255; (16 + 4) * 4])
+        .with_dimensions((4, 4))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_mipmaps(MipGen::Provided(2))
+        .build(&vust)
+        .unwrap();
+    assert_eq!(provided_texture.mip_levels(), 2);
+
+    let result = Texture::builder()
+        .with_data(&[255; 17 * 4])
+        .with_dimensions((4, 4))
+        .with_mipmaps(MipGen::Provided(2))
+        .build(&vust);
+    assert!(matches!(result, Err(VustError::InvalidTextureData)));
+
+    drop(auto_texture);
+    drop(provided_texture);
+    vust.wait_idle().unwrap();
+}
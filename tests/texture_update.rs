@@ -0,0 +1,138 @@
+//! Region updates recorded on the render thread, drawn onto the whole surface to check they landed
+
+use std::mem::size_of;
+
+use ash::vk;
+use vust::{buffer::Buffer, error::VustError, pipeline::{DescriptorSetBinding, DescriptorSetLayout, GraphicsPipeline, GraphicsPipelineCreateInfo}, texture::Texture, write_descriptor_info::WriteDescriptorInfo};
+
+mod common;
+
+#[test]
+fn texture_update() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Texture Update Test", 64, 64);
+
+    // opaque, the default pipeline blends with alpha
+    let texels = |value: u8, count: usize| [value, value, value, 255].repeat(count);
+
+    let texture = Texture::builder()
+        .with_name("Streamed Texture")
+        .with_data(&texels(0, 16 * 16))
+        .with_dimensions((16, 16))
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_filter(vk::Filter::NEAREST)
+        .build(&vust)
+        .unwrap();
+    assert_eq!(texture.dimensions(), (16, 16));
+
+    let pipeline = GraphicsPipeline::new(&vust, GraphicsPipelineCreateInfo {
+        name: "texture update pipeline".to_string(),
+        vertex_bin: include_bytes!("texture_shaders/default.vert.spv").to_vec(),
+        fragment_bin: include_bytes!("texture_shaders/default.frag.spv").to_vec(),
+        vertex_binding_descriptions: vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(0)
+                .stride((size_of::<f32>() * 4) as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build()
+        ],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .offset(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .offset(8)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build()
+        ],
+        topology: vk::PrimitiveTopology::TRIANGLE_STRIP,
+        viewport: vust::pipeline::Viewport::Static {
+            x: 0.0,
+            y: 0.0,
+            width: 64.0,
+            height: 64.0,
+            min_depth: 0.0,
+            max_depth: 1.0
+        },
+        scissor: vust::pipeline::Scissor::Static {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64
+        },
+        polygon_mode: vk::PolygonMode::FILL,
+        cull_mode: vust::pipeline::CullMode::None,
+        descriptor_set_layout: Some(DescriptorSetLayout {
+            bindings: vec![
+                DescriptorSetBinding {
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT
+                }
+            ]
+        }),
+        depth_stencil: Default::default(),
+        multisample: Default::default()
+    }).unwrap();
+    let descriptor = pipeline.create_descriptor(&vust).unwrap();
+    vust.update_descriptor_binding(&descriptor, 0, WriteDescriptorInfo::Image { image_view: texture.view(), sampler: texture.sampler() }).unwrap();
+
+    // covers the whole surface, each texel ends up 4x4 pixels
+    let quad_buffer = Buffer::builder()
+        .with_name("Quad Vertex Buffer")
+        .with_usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+        .with_memory_location(vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)
+        .with_data(&[
+            [-1.0f32, -1.0, 0.0, 0.0],
+            [-1.0, 1.0, 0.0, 1.0],
+            [1.0, -1.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0, 1.0]
+        ])
+        .build_typed(&vust).unwrap();
+
+    // updated every frame, more often than there are frames in flight
+    let mut capture = None;
+    for frame in 0..6u8 {
+        texture.update_region((frame as u32, 4), (8, 8), &texels(frame * 40, 8 * 8)).unwrap();
+
+        if frame == 5 {
+            capture = Some(vust.capture_frame().unwrap());
+        }
+
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.bind_pipeline(pipeline.handle()).unwrap();
+        vust.bind_descriptor_set(pipeline.pipeline_layout(), &descriptor).unwrap();
+        vust.bind_vertex_buffer(quad_buffer.handle()).unwrap();
+        vust.draw(4).unwrap();
+        vust.render_surface().unwrap();
+    }
+
+    // the last update is drawn in the same frame, the earlier ones are only left where it doesnt overlap them
+    let captured_image = capture.unwrap().recv().unwrap();
+    let texel = |x: usize, y: usize| {
+        let pixel = ((y * 4 + 2) * 64 + x * 4 + 2) * 4;
+        captured_image.pixels[pixel..pixel + 3].to_vec()
+    };
+    assert_eq!(texel(8, 8), [200; 3]);
+    assert_eq!(texel(4, 8), [160; 3]);
+    assert_eq!(texel(0, 8), [0; 3]);
+    assert_eq!(texel(14, 8), [0; 3]);
+    assert_eq!(texel(8, 1), [0; 3]);
+
+    let result = texture.update_region((12, 0), (8, 8), &[0; 8 * 8 * 4]);
+    assert!(matches!(result, Err(VustError::TextureRegionOutOfBounds { .. })));
+    // offset + extent wraps around to 4
+    let result = texture.update_region((u32::MAX - 3, 0), (8, 8), &[0; 8 * 8 * 4]);
+    assert!(matches!(result, Err(VustError::TextureRegionOutOfBounds { .. })));
+
+    let result = texture.update_region((0, 0), (8, 8), &[0; 8 * 8 * 3]);
+    assert!(matches!(result, Err(VustError::InvalidTextureData)));
+
+    drop(quad_buffer);
+    drop(texture);
+    vust.wait_idle().unwrap();
+}
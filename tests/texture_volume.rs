@@ -0,0 +1,60 @@
+//! 3d textures
+
+use ash::vk;
+use vust::{error::VustError, texture::{MipGen, Texture, TextureKind}};
+
+mod common;
+
+#[test]
+fn texture_volume() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Texture Volume Test", 64, 64);
+
+    let volume = Texture::builder()
+        .with_name("Volume")
+        .with_data(&[255; 16 * 16 * 4 * 4])
+        .with_dimensions((16, 16))
+        .with_depth(4)
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_filter(vk::Filter::LINEAR)
+        .with_mipmaps(MipGen::Auto)
+        .build(&vust)
+        .unwrap();
+    assert_eq!(volume.kind(), TextureKind::Texture3D);
+    assert_eq!(volume.depth(), 4);
+    assert_eq!(volume.layers(), 1);
+    assert_eq!(volume.mip_levels(), 5);
+
+    // 8x8x2 then 4x4x1, the depth stops at 1
+    let provided = Texture::builder()
+        .with_name("Provided Volume")
+        .with_data(&[0; (8 * 8 * 2 + 4 * 4) * 4])
+        .with_dimensions((8, 8))
+        .with_depth(2)
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .with_mipmaps(MipGen::Provided(2))
+        .build(&vust)
+        .unwrap();
+    assert_eq!(provided.mip_levels(), 2);
+
+    // the layer is the depth slice
+    volume.update_layer_region(3, (0, 0), (16, 16), &[0; 16 * 16 * 4]).unwrap();
+    let result = volume.update_layer_region(4, (0, 0), (16, 16), &[0; 16 * 16 * 4]);
+    assert!(matches!(result, Err(VustError::TextureLayerOutOfBounds { layer: 4, layers: 4 })));
+
+    vust.reset_command_buffer().unwrap();
+    vust_syncer.sync().unwrap();
+    vust.render_surface().unwrap();
+
+    let result = Texture::builder()
+        .with_data(&[0; 4 * 4 * 2 * 4 * 2])
+        .with_dimensions((4, 4))
+        .with_depth(2)
+        .with_layers(2)
+        .with_format(vk::Format::R8G8B8A8_UNORM)
+        .build(&vust);
+    assert!(matches!(result, Err(VustError::InvalidTextureData)));
+
+    drop(volume);
+    drop(provided);
+    vust.wait_idle().unwrap();
+}
@@ -1,33 +1,26 @@
-/// Simple triangle with different colored vertices
+//! Simple triangle with different colored vertices
 
-use std::{mem::size_of, ptr::null};
+use std::mem::size_of;
 
 use ash::vk;
 use glfw::fail_on_errors;
 use vust::{buffer::Buffer, create_info::VustCreateInfo, pipeline::GraphicsPipeline, Vust};
-use winapi::um::libloaderapi::GetModuleHandleW;
 
 #[test]
 fn triangle() {
     let mut glfw = glfw::init(glfw::fail_on_errors!()).unwrap();
-    glfw.window_hint(glfw::WindowHint::Resizable(false));
     glfw.window_hint(glfw::WindowHint::ClientApi(glfw::ClientApiHint::NoApi));
 
-    let (window, _) = glfw.create_window(800, 600, "Vust Triangle Test", glfw::WindowMode::Windowed).unwrap();
+    let (mut window, events) = glfw.create_window(800, 600, "Vust Triangle Test", glfw::WindowMode::Windowed).unwrap();
+    window.set_framebuffer_size_polling(true);
 
     let vust_create_info = VustCreateInfo::default()
         .with_app_name("Vust Triangle Test")
         .with_app_version(vust::make_api_version(0, 0, 1, 0))
-        .with_extensions(glfw.get_required_instance_extensions().unwrap())
-        .with_surface_create_info(
-            vust::create_info::SurfaceCreateInfo::Win32 {
-                hinstance: unsafe { GetModuleHandleW(null()).cast() },
-                hwnd: window.get_win32_window()
-            }
-        )
+        .with_window(&window).unwrap()
         .with_framebuffer_size((window.get_framebuffer_size().0 as usize, window.get_framebuffer_size().1 as usize));
 
-    let (mut vust, vust_syncer) = Vust::new(vust_create_info);
+    let (mut vust, vust_syncer) = Vust::new(vust_create_info).unwrap();
 
     let pipeline = GraphicsPipeline::new(
         &vust,
@@ -74,9 +67,10 @@ fn triangle() {
             polygon_mode: vk::PolygonMode::FILL,
             cull_mode: vust::pipeline::CullMode::None,
             descriptor_set_layout: None,
-            enable_depth_test: false
+            depth_stencil: Default::default(),
+            multisample: Default::default()
         }
-    );
+    ).unwrap();
 
     let triangle_buffer = Buffer::builder()
         .with_name("Triangle Buffer")
@@ -87,18 +81,23 @@ fn triangle() {
             0.5, -0.5, 0.0, 1.0, 0.0,
             0.0, 0.5, 0.0, 0.0, 1.0
         ])
-        .build(&mut vust, true);
+        .build(&mut vust, true).unwrap();
 
     while !window.should_close() {
         glfw.poll_events();
+        for (_, event) in glfw::flush_messages(&events) {
+            if let glfw::WindowEvent::FramebufferSize(width, height) = event {
+                vust.resize(width as u32, height as u32).unwrap();
+            }
+        }
 
-        vust.reset_command_buffer();
-        vust_syncer.sync();
-        vust.bind_pipeline(pipeline.handle());
-        vust.bind_vertex_buffer(triangle_buffer.handle());
-        vust.draw(3);
-        vust.render_surface();
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        vust.bind_pipeline(pipeline.handle()).unwrap();
+        vust.bind_vertex_buffer(triangle_buffer.handle()).unwrap();
+        vust.draw(3).unwrap();
+        vust.render_surface().unwrap();
     }
 
-    vust.wait_idle();
+    vust.wait_idle().unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+//! Uniform ring offsets and overflow
+
+use vust::{error::VustError, uniform_ring::UniformRing};
+
+mod common;
+
+#[test]
+fn uniform_ring() {
+    let (mut vust, vust_syncer) = common::headless_vust("Vust Uniform Ring Test", 64, 64);
+
+    // room for 4 mat4s, rounded up to the offset alignment
+    let mut uniform_ring = UniformRing::new(&vust, 256).unwrap();
+    let model = [1.0f32; 16];
+
+    for _ in 0..3 {
+        vust.reset_command_buffer().unwrap();
+        vust_syncer.sync().unwrap();
+        uniform_ring.begin_frame();
+
+        let (first_offset, size) = uniform_ring.allocate(&model).unwrap();
+        let (second_offset, _) = uniform_ring.allocate(&model).unwrap();
+        assert_eq!(size, 64);
+        assert!(second_offset > first_offset);
+
+        vust.render_surface().unwrap();
+    }
+
+    uniform_ring.begin_frame();
+    let result = (0..1024).try_for_each(|_| uniform_ring.allocate(&model).map(|_| ()));
+    assert!(matches!(result, Err(VustError::BufferOverflow { .. })));
+
+    drop(uniform_ring);
+    vust.wait_idle().unwrap();
+}